@@ -129,6 +129,7 @@ async fn serve_handshake(
                     version_string: "compression-bypass-bench".to_string(),
                     executable_path: PathBuf::from("/bin/wezterm"),
                     config_file_path: None,
+                    server_epoch: 0,
                 }),
                 Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
                 _ => continue,
@@ -108,6 +108,7 @@ async fn setup_client(
                                 version_string: "pdu-pipeline-bench".to_string(),
                                 executable_path: PathBuf::from("/bin/wezterm"),
                                 config_file_path: None,
+                                server_epoch: 0,
                             }),
                         ));
                     }
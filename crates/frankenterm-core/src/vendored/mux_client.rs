@@ -209,6 +209,8 @@ impl DirectMuxClient {
         let response = self
             .send_request(Pdu::GetPaneRenderChanges(GetPaneRenderChanges {
                 pane_id: pane_id as usize,
+                since_seqno: None,
+                want_bonus_lines: true,
             }))
             .await?;
         match response {
@@ -230,6 +232,7 @@ impl DirectMuxClient {
             .send_request(Pdu::GetLines(GetLines {
                 pane_id: pane_id as usize,
                 lines,
+                cols: None,
             }))
             .await?;
         match response {
@@ -311,6 +314,7 @@ impl DirectMuxClient {
             .send_request(Pdu::SetClientId(SetClientId {
                 client_id,
                 is_proxy: false,
+                capabilities: codec::ClientCapabilities::default(),
             }))
             .await?;
         match response {
@@ -341,6 +345,8 @@ impl DirectMuxClient {
             .map(|pane_id| {
                 Pdu::GetPaneRenderChanges(GetPaneRenderChanges {
                     pane_id: *pane_id as usize,
+                    since_seqno: None,
+                    want_bonus_lines: true,
                 })
             })
             .collect::<Vec<_>>();
@@ -977,6 +983,7 @@ mod tests {
                                 version_string: "wezterm-test".to_string(),
                                 executable_path: PathBuf::from("/bin/wezterm"),
                                 config_file_path: None,
+                                server_epoch: 0,
                             };
                             Pdu::GetCodecVersionResponse(payload)
                         }
@@ -1378,6 +1385,7 @@ mod tests {
                                     version_string: "compression-fallback-test".to_string(),
                                     executable_path: PathBuf::from("/bin/wezterm"),
                                     config_file_path: None,
+                                    server_epoch: 0,
                                 })
                             }
                             Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
@@ -1569,6 +1577,7 @@ mod tests {
                     version_string: "incompatible-wezterm".to_string(),
                     executable_path: PathBuf::from("/bin/wezterm"),
                     config_file_path: None,
+                    server_epoch: 0,
                 });
                 let mut out = Vec::new();
                 response.encode(&mut out, decoded.serial).expect("encode");
@@ -1688,6 +1697,7 @@ mod tests {
                                 version_string: "test".to_string(),
                                 executable_path: PathBuf::from("/bin/wezterm"),
                                 config_file_path: None,
+                                server_epoch: 0,
                             })
                         }
                         Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
@@ -1798,6 +1808,7 @@ mod tests {
                                 version_string: "test".to_string(),
                                 executable_path: PathBuf::from("/bin/wezterm"),
                                 config_file_path: None,
+                                server_epoch: 0,
                             })
                         }
                         Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
@@ -481,6 +481,7 @@ mod tests {
                                         version_string: "mock-mux-pool-test".to_string(),
                                         executable_path: PathBuf::from("/bin/wezterm"),
                                         config_file_path: None,
+                                        server_epoch: 0,
                                     })
                                 }
                                 Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
@@ -572,6 +573,7 @@ mod tests {
                                         version_string: "mock-mux-pool-test".to_string(),
                                         executable_path: PathBuf::from("/bin/wezterm"),
                                         config_file_path: None,
+                                        server_epoch: 0,
                                     })
                                 }
                                 Pdu::SetClientId(_) => Pdu::UnitResponse(UnitResponse {}),
@@ -7,13 +7,16 @@
 
 use std::cmp::PartialEq;
 use std::fmt::Debug;
+use std::iter::FromIterator;
 
 /// Represents a (mostly) "proper" binary tree; each Node has 0 or 2 children,
 /// but there is a special case where the tree is rooted with a single leaf node.
 /// Non-leaf nodes in the tree can be labelled with an optional node data type `N`,
 /// which defaults to `()`.
 /// Leaf nodes have a required leaf data type `L`.
+#[derive(Default)]
 pub enum Tree<L, N = ()> {
+    #[default]
     Empty,
     Node {
         left: Box<Self>,
@@ -170,9 +173,127 @@ impl<'a, L, N> std::iter::Iterator for ParentIterator<'a, L, N> {
     }
 }
 
+/// Describes a violation of the invariant that each `Node` in a
+/// `Tree` has two non-empty children; see `Tree::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeError {
+    /// The node reached by following `path` from the root has an
+    /// `Empty` child on the `side` branch.
+    EmptyChild {
+        path: Vec<PathBranch>,
+        side: PathBranch,
+    },
+}
+
+impl std::fmt::Display for TreeError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn branch_name(branch: &PathBranch) -> &'static str {
+            match branch {
+                PathBranch::IsLeft => "left",
+                PathBranch::IsRight => "right",
+            }
+        }
+
+        match self {
+            Self::EmptyChild { path, side } => {
+                write!(fmt, "node at ")?;
+                if path.is_empty() {
+                    write!(fmt, "root")?;
+                } else {
+                    let path: Vec<_> = path.iter().map(branch_name).collect();
+                    write!(fmt, "{}", path.join("/"))?;
+                }
+                write!(fmt, " has an empty {} child", branch_name(side))
+            }
+        }
+    }
+}
+
+impl std::error::Error for TreeError {}
+
+/// Iterates the subtrees of a `Tree` in preorder; see `Tree::preorder`.
+/// Unlike repeatedly calling `Cursor::preorder_next`, once exhausted
+/// it keeps returning `None`.
+pub struct PreorderIter<'a, L, N> {
+    stack: Vec<&'a Tree<L, N>>,
+}
+
+impl<'a, L, N> std::iter::Iterator for PreorderIter<'a, L, N> {
+    type Item = &'a Tree<L, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = self.stack.pop()?;
+        if let Tree::Node { left, right, .. } = tree {
+            self.stack.push(right);
+            self.stack.push(left);
+        }
+        Some(tree)
+    }
+}
+
+impl<'a, L, N> std::iter::FusedIterator for PreorderIter<'a, L, N> {}
+
+/// Iterates the subtrees of a `Tree` in postorder; see `Tree::postorder`.
+/// Unlike repeatedly calling `Cursor::postorder_next`, once exhausted
+/// it keeps returning `None`.
+pub struct PostorderIter<'a, L, N> {
+    /// Each entry records whether its children have been pushed yet
+    stack: Vec<(&'a Tree<L, N>, bool)>,
+}
+
+impl<'a, L, N> std::iter::Iterator for PostorderIter<'a, L, N> {
+    type Item = &'a Tree<L, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (tree, expanded) = self.stack.pop()?;
+            match tree {
+                Tree::Node { left, right, .. } if !expanded => {
+                    self.stack.push((tree, true));
+                    self.stack.push((right, false));
+                    self.stack.push((left, false));
+                }
+                _ => return Some(tree),
+            }
+        }
+    }
+}
+
+impl<'a, L, N> std::iter::FusedIterator for PostorderIter<'a, L, N> {}
+
+/// Iterates the leaves of a `Tree` in preorder, along with the
+/// branches taken from the root to reach each one; see
+/// `Tree::leaves_with_paths`.
+pub struct LeafPathIter<'a, L, N> {
+    stack: Vec<(&'a Tree<L, N>, Vec<PathBranch>)>,
+}
+
+impl<'a, L, N> std::iter::Iterator for LeafPathIter<'a, L, N> {
+    type Item = (&'a L, Vec<PathBranch>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (tree, path) = self.stack.pop()?;
+            match tree {
+                Tree::Empty => {}
+                Tree::Leaf(l) => return Some((l, path)),
+                Tree::Node { left, right, .. } => {
+                    let mut right_path = path.clone();
+                    right_path.push(PathBranch::IsRight);
+                    self.stack.push((right, right_path));
+                    let mut left_path = path;
+                    left_path.push(PathBranch::IsLeft);
+                    self.stack.push((left, left_path));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, L, N> std::iter::FusedIterator for LeafPathIter<'a, L, N> {}
+
 impl<L, N> Tree<L, N> {
     /// Construct a new empty tree
-    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self::Empty
     }
@@ -197,6 +318,204 @@ impl<L, N> Tree<L, N> {
             Self::Node { left, right, .. } => left.num_leaves() + right.num_leaves(),
         }
     }
+
+    /// Returns the number of edges on the longest path from the root
+    /// to a leaf.  Both the empty tree and a single leaf have height 0.
+    pub fn height(&self) -> usize {
+        match self {
+            Self::Empty | Self::Leaf(_) => 0,
+            Self::Node { left, right, .. } => 1 + left.height().max(right.height()),
+        }
+    }
+
+    /// Returns an iterator over every node and leaf of the tree in
+    /// preorder.  The empty tree yields nothing.
+    pub fn preorder(&self) -> PreorderIter<'_, L, N> {
+        PreorderIter {
+            stack: if self.is_empty() { vec![] } else { vec![self] },
+        }
+    }
+
+    /// Returns an iterator over every node and leaf of the tree in
+    /// postorder.  The empty tree yields nothing.
+    pub fn postorder(&self) -> PostorderIter<'_, L, N> {
+        PostorderIter {
+            stack: if self.is_empty() {
+                vec![]
+            } else {
+                vec![(self, false)]
+            },
+        }
+    }
+
+    /// Returns references to the leaves of the tree in preorder
+    pub fn leaves(&self) -> Vec<&L> {
+        fn collect<'a, L, N>(tree: &'a Tree<L, N>, leaves: &mut Vec<&'a L>) {
+            match tree {
+                Tree::Empty => {}
+                Tree::Leaf(l) => leaves.push(l),
+                Tree::Node { left, right, .. } => {
+                    collect(left, leaves);
+                    collect(right, leaves);
+                }
+            }
+        }
+
+        let mut leaves = vec![];
+        collect(self, &mut leaves);
+        leaves
+    }
+
+    /// Returns an iterator over the leaves of the tree in preorder,
+    /// each paired with the branches taken from the root to reach
+    /// it.  A tree that is a single leaf yields it with an empty path.
+    pub fn leaves_with_paths(&self) -> LeafPathIter<'_, L, N> {
+        LeafPathIter {
+            stack: vec![(self, vec![])],
+        }
+    }
+
+    /// Calls `f` on each leaf from left to right, stopping at and
+    /// returning the first `Err` that it produces.
+    pub fn try_for_each_leaf<E>(&self, mut f: impl FnMut(&L) -> Result<(), E>) -> Result<(), E> {
+        fn visit<L, N, E>(
+            tree: &Tree<L, N>,
+            f: &mut impl FnMut(&L) -> Result<(), E>,
+        ) -> Result<(), E> {
+            match tree {
+                Tree::Empty => Ok(()),
+                Tree::Leaf(l) => f(l),
+                Tree::Node { left, right, .. } => {
+                    visit(left, f)?;
+                    visit(right, f)
+                }
+            }
+        }
+
+        visit(self, &mut f)
+    }
+
+    /// Checks that every `Node` in the tree has two non-empty
+    /// children, which the traversal and `unsplit_leaf` code relies
+    /// upon.  Returns an error describing the first violation found
+    /// in preorder.  The empty tree and a single leaf are valid.
+    pub fn validate(&self) -> Result<(), TreeError> {
+        fn check<L, N>(tree: &Tree<L, N>, path: &mut Vec<PathBranch>) -> Result<(), TreeError> {
+            if let Tree::Node { left, right, .. } = tree {
+                for (side, child) in [(PathBranch::IsLeft, left), (PathBranch::IsRight, right)] {
+                    if child.is_empty() {
+                        return Err(TreeError::EmptyChild {
+                            path: path.clone(),
+                            side,
+                        });
+                    }
+                    path.push(side);
+                    check(child, path)?;
+                    path.pop();
+                }
+            }
+            Ok(())
+        }
+
+        check(self, &mut vec![])
+    }
+
+    /// Consume the tree and rebuild it as a balanced tree of minimal
+    /// height, preserving the preorder sequence of its leaves.
+    /// Node data cannot be meaningfully mapped onto the new shape,
+    /// so every node in the result has `data: None`.
+    /// The empty tree and a single leaf are returned unchanged.
+    pub fn rebalanced(self) -> Self {
+        fn take_leaves<L, N>(tree: Tree<L, N>, leaves: &mut Vec<L>) {
+            match tree {
+                Tree::Empty => {}
+                Tree::Leaf(l) => leaves.push(l),
+                Tree::Node { left, right, .. } => {
+                    take_leaves(*left, leaves);
+                    take_leaves(*right, leaves);
+                }
+            }
+        }
+
+        match self {
+            Self::Empty | Self::Leaf(_) => self,
+            node => {
+                let mut leaves = vec![];
+                take_leaves(node, &mut leaves);
+                build_balanced(leaves)
+            }
+        }
+    }
+}
+
+impl<L: std::fmt::Display, N: std::fmt::Display> Tree<L, N> {
+    /// Render the tree as indented ASCII art, one entry per line,
+    /// for use when debugging layouts.  Leaves show their value and
+    /// nodes show their data, if any; eg:
+    ///
+    /// ```text
+    /// node 10
+    /// +-- leaf 1
+    /// `-- node
+    ///     +-- leaf 2
+    ///     `-- leaf 3
+    /// ```
+    pub fn to_ascii_art(&self) -> String {
+        fn render<L: std::fmt::Display, N: std::fmt::Display>(
+            tree: &Tree<L, N>,
+            prefix: &str,
+            out: &mut String,
+        ) {
+            match tree {
+                Tree::Empty => out.push_str("empty\n"),
+                Tree::Leaf(value) => out.push_str(&format!("leaf {}\n", value)),
+                Tree::Node { left, right, data } => {
+                    match data {
+                        Some(data) => out.push_str(&format!("node {}\n", data)),
+                        None => out.push_str("node\n"),
+                    }
+                    out.push_str(prefix);
+                    out.push_str("+-- ");
+                    render(left, &format!("{}|   ", prefix), out);
+                    out.push_str(prefix);
+                    out.push_str("`-- ");
+                    render(right, &format!("{}    ", prefix), out);
+                }
+            }
+        }
+
+        let mut out = String::new();
+        render(self, "", &mut out);
+        out
+    }
+}
+
+/// Builds a tree of minimal height from a non-empty list of leaves,
+/// preserving their order.  Nodes are assigned `data: None`.
+fn build_balanced<L, N>(mut leaves: Vec<L>) -> Tree<L, N> {
+    if leaves.len() == 1 {
+        return Tree::Leaf(leaves.pop().unwrap());
+    }
+    let right = leaves.split_off(leaves.len().div_ceil(2));
+    Tree::Node {
+        left: Box::new(build_balanced(leaves)),
+        right: Box::new(build_balanced(right)),
+        data: None,
+    }
+}
+
+/// Builds a balanced tree whose leaves, in preorder, are the items
+/// yielded by the iterator.  Nodes are assigned `data: None`.
+/// An empty iterator produces `Tree::Empty`.
+impl<L, N> FromIterator<L> for Tree<L, N> {
+    fn from_iter<I: IntoIterator<Item = L>>(iter: I) -> Self {
+        let leaves: Vec<L> = iter.into_iter().collect();
+        if leaves.is_empty() {
+            Self::Empty
+        } else {
+            build_balanced(leaves)
+        }
+    }
 }
 
 impl<L, N> Cursor<L, N> {
@@ -214,6 +533,12 @@ impl<L, N> Cursor<L, N> {
         &self.it
     }
 
+    /// Returns the number of leaves in the subtree at the current
+    /// cursor position
+    pub fn subtree_leaf_count(&self) -> usize {
+        self.subtree().num_leaves()
+    }
+
     /// Returns true if the current position is a leaf node
     pub fn is_leaf(&self) -> bool {
         matches!(&*self.it, Tree::Leaf(_))
@@ -249,6 +574,14 @@ impl<L, N> Cursor<L, N> {
         }
     }
 
+    /// Replaces the subtree at the current position with `new`.
+    /// Consumes the cursor and returns a new cursor positioned at
+    /// the grafted subtree, along with the detached old subtree.
+    pub fn replace_subtree(mut self, new: Tree<L, N>) -> (Self, Tree<L, N>) {
+        let old = std::mem::replace(&mut *self.it, new);
+        (self, old)
+    }
+
     /// If the current position is a leaf node, return a mutable
     /// reference to the leaf data, else `None`.
     pub fn leaf_mut(&mut self) -> Option<&mut L> {
@@ -275,6 +608,26 @@ impl<L, N> Cursor<L, N> {
         ParentIterator { path: &*self.path }
     }
 
+    /// Returns the node data of the immediate parent of the current
+    /// position, or `None` if the current position is the root.
+    pub fn parent_data(&self) -> Option<&Option<N>> {
+        self.path_to_root().next().map(|(_, data)| data)
+    }
+
+    /// Fold `f` over the `path_to_root`, starting from the parent of
+    /// the current position and ending at the root.  Each call receives
+    /// the branch taken from that node towards the current position,
+    /// along with its data; eg: to combine the split ratios of the
+    /// nodes containing a leaf.
+    pub fn fold_path_to_root<A>(
+        &self,
+        init: A,
+        mut f: impl FnMut(A, PathBranch, &Option<N>) -> A,
+    ) -> A {
+        self.path_to_root()
+            .fold(init, |acc, (branch, data)| f(acc, branch, data))
+    }
+
     /// If the current position is not a leaf node, assign the
     /// node data to the supplied value.
     /// Consumes the cursor and returns a new cursor representing the
@@ -321,6 +674,43 @@ impl<L, N> Cursor<L, N> {
         }
     }
 
+    /// Remove the nth (preorder) leaf of the current subtree, unsplitting
+    /// its parent as `unsplit_leaf` does; the parent node's data is
+    /// discarded.
+    /// On success, yields the revised cursor, which points to the newly
+    /// unsplit node, along with the removed leaf value.
+    /// Yields `Err` containing the unchanged cursor if there is no nth
+    /// leaf, or if the tree consists of just that single leaf.
+    pub fn remove_nth_leaf(self, n: usize) -> Result<(Self, L), Self> {
+        if n >= self.subtree_leaf_count() || (self.is_top() && self.is_leaf()) {
+            return Err(self);
+        }
+        let (cursor, leaf, _data) = self.go_to_nth_leaf(n)?.unsplit_leaf()?;
+        Ok((cursor, leaf))
+    }
+
+    /// Move to the nth (preorder) leaf of the current subtree and
+    /// split it, inserting `value` on the specified `side` of it.
+    /// On success, yields the revised cursor, which points to the
+    /// newly created node.
+    /// Yields `Err` containing the unchanged cursor if there is no
+    /// nth leaf.
+    pub fn insert_beside_nth_leaf(
+        self,
+        n: usize,
+        value: L,
+        side: PathBranch,
+    ) -> Result<Self, Self> {
+        if n >= self.subtree_leaf_count() {
+            return Err(self);
+        }
+        let leaf = self.go_to_nth_leaf(n)?;
+        match side {
+            PathBranch::IsLeft => leaf.split_leaf_and_insert_left(value),
+            PathBranch::IsRight => leaf.split_leaf_and_insert_right(value),
+        }
+    }
+
     pub fn split_node_and_insert_left(self, to_insert: L) -> Result<Self, Self> {
         match *self.it {
             Tree::Node { left, right, data } => Ok(Self {
@@ -468,7 +858,7 @@ impl<L, N> Cursor<L, N> {
     /// yields `Err` with the newly adjusted cursor; calling `preorder_next`
     /// after it has yielded `Err` can potentially yield `Ok` with previously
     /// visited nodes, so the caller must take care to stop iterating when
-    /// `Err` is received!
+    /// `Err` is received!  `Tree::preorder` is a safer alternative.
     pub fn preorder_next(mut self) -> Result<Self, Self> {
         // Since we are a "proper" binary tree, we know we cannot have
         // difficult cases such as a left without a right or vice versa.
@@ -502,7 +892,7 @@ impl<L, N> Cursor<L, N> {
     /// yields `Err` with the newly adjusted cursor; calling `postorder_next`
     /// after it has yielded `Err` can potentially yield `Ok` with previously
     /// visited nodes, so the caller must take care to stop iterating when
-    /// `Err` is received!
+    /// `Err` is received!  `Tree::postorder` is a safer alternative.
     pub fn postorder_next(mut self) -> Result<Self, Self> {
         // Since we are a "proper" binary tree, we know we cannot have
         // difficult cases such as a left without a right or vice versa.
@@ -529,6 +919,45 @@ impl<L, N> Cursor<L, N> {
         }
     }
 
+    /// Move the current position to the next in an inorder traversal.
+    /// Returns the modified cursor position.
+    ///
+    /// An inorder traversal begins at the leftmost leaf, so callers
+    /// wanting to visit the whole tree should start from
+    /// `go_to_nth_leaf(0)` rather than the root.
+    ///
+    /// In the case where there are no more nodes in the inorder traversal,
+    /// yields `Err` with the newly adjusted cursor; calling `inorder_next`
+    /// after it has yielded `Err` can potentially yield `Ok` with previously
+    /// visited nodes, so the caller must take care to stop iterating when
+    /// `Err` is received!
+    pub fn inorder_next(mut self) -> Result<Self, Self> {
+        // Since we are a "proper" binary tree, we know we cannot have
+        // difficult cases such as a left without a right or vice versa.
+
+        if self.is_leaf() {
+            // while (We were on the right)
+            loop {
+                if self.is_top() {
+                    return Err(self);
+                }
+
+                if self.is_left() {
+                    return self.go_up();
+                }
+
+                self = self.go_up()?;
+            }
+        } else {
+            // Descend to the leftmost leaf of the right subtree
+            self = self.go_right()?;
+            while !self.is_leaf() {
+                self = self.go_left()?;
+            }
+            Ok(self)
+        }
+    }
+
     /// Move to the nth (preorder) leaf from the current position.
     pub fn go_to_nth_leaf(mut self, n: usize) -> Result<Self, Self> {
         let mut next = 0;
@@ -543,15 +972,61 @@ impl<L, N> Cursor<L, N> {
         }
     }
 
-    /// Consume the cursor and return the root of the Tree
-    pub fn tree(mut self) -> Tree<L, N> {
+    /// Follow `path` down from the current position, going left or
+    /// right at each step; eg: to return to a leaf whose path was
+    /// recorded by `Tree::leaves_with_paths`.
+    /// If a step can't be taken because the position is a leaf,
+    /// yields `Err` containing the cursor at its original position.
+    pub fn go_to_path(mut self, path: &[PathBranch]) -> Result<Self, Self> {
+        for (depth, branch) in path.iter().enumerate() {
+            let step = match branch {
+                PathBranch::IsLeft => self.go_left(),
+                PathBranch::IsRight => self.go_right(),
+            };
+            self = match step {
+                Ok(cursor) => cursor,
+                Err(mut cursor) => {
+                    for _ in 0..depth {
+                        cursor = cursor.go_up().unwrap_or_else(|cursor| cursor);
+                    }
+                    return Err(cursor);
+                }
+            };
+        }
+        Ok(self)
+    }
+
+    /// Move to the leaf found at `ratio` of the way through the (preorder)
+    /// leaves of the current subtree, where 0.0 is the first leaf and
+    /// 1.0 is the last.  Out of range ratios are clamped.
+    /// Fails only if the subtree has no leaves.
+    pub fn go_to_leaf_at_ratio(self, ratio: f64) -> Result<Self, Self> {
+        let num_leaves = self.subtree_leaf_count();
+        if num_leaves == 0 {
+            return Err(self);
+        }
+        let ratio = ratio.clamp(0.0, 1.0);
+        let n = (ratio * (num_leaves - 1) as f64).floor() as usize;
+        self.go_to_nth_leaf(n)
+    }
+
+    /// Move the current position up to the root of the tree, so that
+    /// navigation can start again from the top.
+    /// Unlike the other movements this cannot fail, as the root is
+    /// always reachable.
+    pub fn go_to_root(mut self) -> Self {
         loop {
             self = match self.go_up() {
                 Ok(up) => up,
-                Err(top) => return *top.it,
+                Err(top) => return top,
             }
         }
     }
+
+    /// Consume the cursor and return the root of the Tree
+    pub fn tree(self) -> Tree<L, N> {
+        *self.go_to_root().it
+    }
 }
 
 #[cfg(test)]
@@ -734,6 +1209,298 @@ mod tests {
         assert_eq!(t.num_leaves(), 2);
     }
 
+    // ── height / leaves / rebalanced ───────────────────────────
+
+    fn comb(n: i32) -> Tree<i32, i32> {
+        let mut t = Tree::Leaf(n);
+        for i in (1..n).rev() {
+            t = Tree::Node {
+                left: Box::new(Tree::Leaf(i)),
+                right: Box::new(t),
+                data: Some(i * 10),
+            };
+        }
+        t
+    }
+
+    #[test]
+    fn height_and_leaves_of_comb() {
+        let t = comb(4);
+        assert_eq!(t.height(), 3);
+        assert_eq!(t.leaves(), vec![&1, &2, &3, &4]);
+        assert_eq!(Tree::<i32>::new().height(), 0);
+        assert!(Tree::<i32>::new().leaves().is_empty());
+    }
+
+    #[test]
+    fn try_for_each_leaf_visits_all_leaves() {
+        let mut visited = vec![];
+        let result: Result<(), ()> = comb(4).try_for_each_leaf(|l| {
+            visited.push(*l);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(visited, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn try_for_each_leaf_stops_at_first_error() {
+        let mut visited = vec![];
+        let result = comb(4).try_for_each_leaf(|l| {
+            visited.push(*l);
+            if visited.len() == 2 {
+                Err(format!("failed on {}", l))
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("failed on 2".to_string()));
+        assert_eq!(visited, vec![1, 2]);
+    }
+
+    #[test]
+    fn try_for_each_leaf_empty_tree() {
+        let result: Result<(), ()> = Tree::<i32>::new().try_for_each_leaf(|_| Err(()));
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn rebalanced_comb_has_minimal_height() {
+        let t = comb(4).rebalanced();
+        assert_eq!(t.height(), 2);
+        assert_eq!(t.leaves(), vec![&1, &2, &3, &4]);
+        assert_eq!(t.num_leaves(), 4);
+    }
+
+    #[test]
+    fn rebalanced_discards_node_data() {
+        let t = comb(3).rebalanced();
+        assert_eq!(
+            t,
+            Tree::Node {
+                left: Box::new(Tree::Node {
+                    left: Box::new(Tree::Leaf(1)),
+                    right: Box::new(Tree::Leaf(2)),
+                    data: None,
+                }),
+                right: Box::new(Tree::Leaf(3)),
+                data: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rebalanced_preserves_order_for_many_leaves() {
+        let t = comb(9).rebalanced();
+        assert_eq!(t.height(), 4);
+        let expected: Vec<i32> = (1..=9).collect();
+        assert_eq!(t.leaves(), expected.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn rebalanced_empty_and_single_leaf_unchanged() {
+        assert!(Tree::<i32>::new().rebalanced().is_empty());
+        assert_eq!(Tree::<i32>::Leaf(7).rebalanced(), Tree::Leaf(7));
+    }
+
+    // ── validate ───────────────────────────────────────────────
+
+    #[test]
+    fn validate_accepts_well_formed_trees() {
+        assert_eq!(Tree::<i32, i32>::Empty.validate(), Ok(()));
+        assert_eq!(Tree::<i32, i32>::Leaf(1).validate(), Ok(()));
+        assert_eq!(comb(5).validate(), Ok(()));
+        assert_eq!(comb(7).rebalanced().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_empty_child() {
+        let t: Tree<i32, i32> = Tree::Node {
+            left: Box::new(Tree::Leaf(1)),
+            right: Box::new(Tree::Node {
+                left: Box::new(Tree::Leaf(2)),
+                right: Box::new(Tree::Empty),
+                data: None,
+            }),
+            data: None,
+        };
+        let err = t.validate().unwrap_err();
+        assert_eq!(
+            err,
+            TreeError::EmptyChild {
+                path: vec![PathBranch::IsRight],
+                side: PathBranch::IsRight,
+            }
+        );
+        assert_eq!(err.to_string(), "node at right has an empty right child");
+
+        let t: Tree<i32, i32> = Tree::Node {
+            left: Box::new(Tree::Empty),
+            right: Box::new(Tree::Leaf(1)),
+            data: None,
+        };
+        assert_eq!(
+            t.validate().unwrap_err().to_string(),
+            "node at root has an empty left child"
+        );
+    }
+
+    // ── to_ascii_art ───────────────────────────────────────────
+
+    #[test]
+    fn to_ascii_art_renders_nested_tree() {
+        let t: Tree<i32, i32> = Tree::Node {
+            left: Box::new(Tree::Leaf(1)),
+            right: Box::new(Tree::Node {
+                left: Box::new(Tree::Leaf(2)),
+                right: Box::new(Tree::Leaf(3)),
+                data: None,
+            }),
+            data: Some(10),
+        };
+        let art = t.to_ascii_art();
+        assert_eq!(
+            art.lines().collect::<Vec<_>>(),
+            vec![
+                "node 10",
+                "+-- leaf 1",
+                "`-- node",
+                "    +-- leaf 2",
+                "    `-- leaf 3",
+            ]
+        );
+
+        let positions: Vec<usize> = ["leaf 1", "leaf 2", "leaf 3"]
+            .iter()
+            .map(|label| art.find(label).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn to_ascii_art_of_comb_indents_left_branches() {
+        let art = Tree::Node {
+            left: Box::new(comb(2)),
+            right: Box::new(Tree::Leaf(3)),
+            data: None,
+        }
+        .to_ascii_art();
+        assert_eq!(
+            art.lines().collect::<Vec<_>>(),
+            vec![
+                "node",
+                "+-- node 10",
+                "|   +-- leaf 1",
+                "|   `-- leaf 2",
+                "`-- leaf 3",
+            ]
+        );
+        assert_eq!(Tree::<i32, i32>::new().to_ascii_art(), "empty\n");
+    }
+
+    // ── PreorderIter / PostorderIter ───────────────────────────
+
+    fn labels(items: Vec<&Tree<i32, i32>>) -> Vec<String> {
+        items
+            .into_iter()
+            .map(|t| match t {
+                Tree::Leaf(l) => format!("leaf {}", l),
+                Tree::Node { data, .. } => format!("node {}", data.unwrap()),
+                Tree::Empty => "empty".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn preorder_iter_visits_each_once() {
+        let t = comb(3);
+        assert_eq!(
+            labels(t.preorder().collect()),
+            vec!["node 10", "leaf 1", "node 20", "leaf 2", "leaf 3"]
+        );
+    }
+
+    #[test]
+    fn postorder_iter_visits_each_once() {
+        let t = comb(3);
+        assert_eq!(
+            labels(t.postorder().collect()),
+            vec!["leaf 1", "leaf 2", "leaf 3", "node 20", "node 10"]
+        );
+    }
+
+    #[test]
+    fn traversal_iters_are_fused() {
+        let t = comb(4);
+        let mut pre = t.preorder();
+        assert_eq!(pre.by_ref().count(), 7);
+        for _ in 0..3 {
+            assert!(pre.next().is_none());
+        }
+        let mut post = t.postorder();
+        assert_eq!(post.by_ref().count(), 7);
+        for _ in 0..3 {
+            assert!(post.next().is_none());
+        }
+    }
+
+    #[test]
+    fn traversal_iters_on_empty_and_single_leaf() {
+        let t = Tree::<i32, i32>::new();
+        assert_eq!(t.preorder().count(), 0);
+        assert_eq!(t.postorder().count(), 0);
+        let t = Tree::<i32, i32>::Leaf(1);
+        assert_eq!(labels(t.preorder().collect()), vec!["leaf 1"]);
+        assert_eq!(labels(t.postorder().collect()), vec!["leaf 1"]);
+    }
+
+    #[test]
+    fn leaves_with_paths_of_comb() {
+        use PathBranch::*;
+
+        let t = comb(3);
+        let paths: Vec<_> = t.leaves_with_paths().collect();
+        assert_eq!(
+            paths,
+            vec![
+                (&1, vec![IsLeft]),
+                (&2, vec![IsRight, IsLeft]),
+                (&3, vec![IsRight, IsRight]),
+            ]
+        );
+
+        let single: Tree<i32, i32> = Tree::Leaf(7);
+        assert_eq!(
+            single.leaves_with_paths().collect::<Vec<_>>(),
+            vec![(&7, vec![])]
+        );
+        assert_eq!(Tree::<i32, i32>::new().leaves_with_paths().count(), 0);
+    }
+
+    // ── Default / FromIterator ─────────────────────────────────
+
+    #[test]
+    fn default_tree_is_empty() {
+        let t: Tree<i32, i32> = Tree::default();
+        assert!(t.is_empty());
+    }
+
+    #[test]
+    fn collect_three_leaves() {
+        let t: Tree<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(t.num_leaves(), 3);
+        assert_eq!(t.leaves(), vec![&1, &2, &3]);
+        assert_eq!(t.height(), 2);
+    }
+
+    #[test]
+    fn collect_zero_and_one_leaves() {
+        let t: Tree<i32> = std::iter::empty().collect();
+        assert_eq!(t, Tree::Empty);
+        let t: Tree<i32> = std::iter::once(5).collect();
+        assert_eq!(t, Tree::Leaf(5));
+    }
+
     // ── Cursor construction ────────────────────────────────────
 
     #[test]
@@ -813,6 +1580,37 @@ mod tests {
         assert!(c.go_up().is_err());
     }
 
+    // ── go_to_path ─────────────────────────────────────────────
+
+    #[test]
+    fn go_to_path_returns_to_leaves_with_paths() {
+        let t = comb(4);
+        let paths: Vec<_> = t
+            .leaves_with_paths()
+            .map(|(leaf, path)| (*leaf, path))
+            .collect();
+        let mut cursor = t.cursor();
+        for (leaf, path) in paths {
+            cursor = cursor.go_to_path(&path).unwrap();
+            assert_eq!(cursor.subtree(), &Tree::Leaf(leaf));
+            cursor = cursor.go_to_root();
+        }
+    }
+
+    #[test]
+    fn go_to_path_through_leaf_fails_unmoved() {
+        use PathBranch::*;
+
+        let cursor = comb(3).cursor().go_right().unwrap();
+        let cursor = cursor.go_to_path(&[IsRight, IsLeft]).unwrap_err();
+        assert!(cursor.is_right());
+        assert_eq!(cursor.subtree_leaf_count(), 2);
+        assert_eq!(cursor.path_to_root().count(), 1);
+
+        let cursor = cursor.go_to_path(&[]).unwrap();
+        assert_eq!(cursor.subtree_leaf_count(), 2);
+    }
+
     // ── go_up ──────────────────────────────────────────────────
 
     #[test]
@@ -983,6 +1781,26 @@ mod tests {
         assert_eq!(t.num_leaves(), 3);
     }
 
+    // ── replace_subtree ────────────────────────────────────────
+
+    #[test]
+    fn replace_subtree_grafts_tree_at_leaf() {
+        let cursor = comb(3).cursor().go_to_nth_leaf(0).unwrap();
+        let graft = Tree::Node {
+            left: Box::new(Tree::Leaf(100)),
+            right: Box::new(Tree::Leaf(101)),
+            data: Some(1000),
+        };
+        let (cursor, old) = cursor.replace_subtree(graft);
+        assert_eq!(old, Tree::Leaf(1));
+        assert_eq!(cursor.subtree_leaf_count(), 2);
+
+        let t = cursor.tree();
+        assert_eq!(t.num_leaves(), 4);
+        assert_eq!(t.leaves(), vec![&100, &101, &2, &3]);
+        assert!(t.validate().is_ok());
+    }
+
     // ── path_to_root ───────────────────────────────────────────
 
     #[test]
@@ -1011,6 +1829,62 @@ mod tests {
         assert_eq!(*path[0].1, Some(10));
     }
 
+    #[test]
+    fn parent_data_of_leaf_and_root() {
+        let c = Tree::<i32, i32>::new()
+            .cursor()
+            .assign_top(1)
+            .unwrap()
+            .split_leaf_and_insert_right(2)
+            .unwrap()
+            .assign_node(Some(5))
+            .unwrap();
+        assert_eq!(c.parent_data(), None);
+
+        let c = c.go_right().unwrap();
+        assert_eq!(c.parent_data(), Some(&Some(5)));
+
+        // comb(3) has node data 10 at the root and 20 below it
+        let c = comb(3).cursor().go_to_nth_leaf(2).unwrap();
+        assert_eq!(c.parent_data(), Some(&Some(20)));
+    }
+
+    #[test]
+    fn fold_path_to_root_accumulates_node_data() {
+        // Treat each node's data as the percentage of its space given
+        // to the left child, and work out the leaf's share of the root
+        let share = |cursor: &Cursor<i32, i32>| {
+            cursor.fold_path_to_root(1.0, |acc, branch, data| {
+                let left = data.unwrap() as f64 / 100.;
+                acc * match branch {
+                    PathBranch::IsLeft => left,
+                    PathBranch::IsRight => 1. - left,
+                }
+            })
+        };
+
+        // comb(3) has node data 10 at the root and 20 below it
+        let cursor = comb(3).cursor().go_to_nth_leaf(1).unwrap();
+        assert!((share(&cursor) - 0.9 * 0.2).abs() < 1e-9);
+        let cursor = cursor.go_to_root().go_to_nth_leaf(2).unwrap();
+        assert!((share(&cursor) - 0.9 * 0.8).abs() < 1e-9);
+
+        let branches = cursor.fold_path_to_root(vec![], |mut acc, branch, data| {
+            acc.push((branch, *data));
+            acc
+        });
+        assert_eq!(
+            branches,
+            vec![
+                (PathBranch::IsRight, Some(20)),
+                (PathBranch::IsRight, Some(10))
+            ]
+        );
+
+        let top = comb(3).cursor();
+        assert_eq!(top.fold_path_to_root(7, |acc, _, _| acc + 1), 7);
+    }
+
     // ── postorder_next ─────────────────────────────────────────
 
     #[test]
@@ -1040,6 +1914,55 @@ mod tests {
         assert_eq!(leaves, vec![2, 1]);
     }
 
+    // ── inorder_next ───────────────────────────────────────────
+
+    #[test]
+    fn inorder_next_three_leaves() {
+        let t = Tree::<i32, i32>::new()
+            .cursor()
+            .assign_top(1)
+            .unwrap()
+            .split_leaf_and_insert_right(3)
+            .unwrap()
+            .assign_node(Some(100))
+            .unwrap()
+            .go_left()
+            .unwrap()
+            .split_leaf_and_insert_right(2)
+            .unwrap()
+            .assign_node(Some(101))
+            .unwrap()
+            .tree();
+
+        let mut visited = vec![];
+        let mut cursor = t.cursor().go_to_nth_leaf(0).unwrap();
+        loop {
+            match cursor.leaf_mut() {
+                Some(leaf) => visited.push(format!("leaf {}", leaf)),
+                None => visited.push(format!("node {}", cursor.node_mut().unwrap().unwrap())),
+            }
+            match cursor.inorder_next() {
+                Ok(c) => cursor = c,
+                Err(c) => {
+                    cursor = c;
+                    break;
+                }
+            }
+        }
+
+        assert_eq!(
+            visited,
+            vec!["leaf 1", "node 101", "leaf 2", "node 100", "leaf 3"]
+        );
+        assert!(cursor.is_top());
+    }
+
+    #[test]
+    fn inorder_next_single_leaf_is_exhausted() {
+        let cursor = Tree::<i32>::new().cursor().assign_top(1).unwrap();
+        assert!(cursor.inorder_next().is_err());
+    }
+
     // ── subtree ────────────────────────────────────────────────
 
     #[test]
@@ -1054,6 +1977,175 @@ mod tests {
         assert_eq!(*c.subtree(), Tree::Empty);
     }
 
+    // ── subtree_leaf_count ─────────────────────────────────────
+
+    #[test]
+    fn subtree_leaf_count_of_inner_subtree() {
+        let t = Tree::<i32, ()>::new()
+            .cursor()
+            .assign_top(1)
+            .unwrap()
+            .split_leaf_and_insert_right(3)
+            .unwrap()
+            .go_left()
+            .unwrap()
+            .split_leaf_and_insert_right(2)
+            .unwrap()
+            .tree();
+        assert_eq!(t.num_leaves(), 3);
+
+        let cursor = t.cursor();
+        assert_eq!(cursor.subtree_leaf_count(), 3);
+
+        let cursor = cursor.go_left().unwrap();
+        assert_eq!(cursor.subtree_leaf_count(), 2);
+        assert_eq!(cursor.subtree_leaf_count(), 2);
+        assert!(cursor.is_left());
+
+        let cursor = cursor.go_left().unwrap();
+        assert_eq!(cursor.subtree_leaf_count(), 1);
+        assert_eq!(cursor.tree().num_leaves(), 3);
+    }
+
+    #[test]
+    fn subtree_leaf_count_empty_cursor() {
+        assert_eq!(Cursor::<i32, ()>::new().subtree_leaf_count(), 0);
+    }
+
+    // ── remove_nth_leaf ────────────────────────────────────────
+
+    #[test]
+    fn remove_nth_leaf_middle_of_three() {
+        let (cursor, removed) = comb(3).cursor().remove_nth_leaf(1).unwrap();
+        assert_eq!(removed, 2);
+        let t = cursor.tree();
+        assert_eq!(t.leaves(), vec![&1, &3]);
+        assert_eq!(t.num_leaves(), 2);
+    }
+
+    #[test]
+    fn remove_nth_leaf_first_and_last() {
+        let (cursor, removed) = comb(3).cursor().remove_nth_leaf(0).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(cursor.tree().leaves(), vec![&2, &3]);
+
+        let (cursor, removed) = comb(3).cursor().remove_nth_leaf(2).unwrap();
+        assert_eq!(removed, 3);
+        assert_eq!(cursor.tree().leaves(), vec![&1, &2]);
+    }
+
+    #[test]
+    fn remove_nth_leaf_out_of_range_is_unchanged() {
+        let cursor = comb(3).cursor().go_right().unwrap();
+        let cursor = cursor.remove_nth_leaf(2).unwrap_err();
+        assert!(cursor.is_right());
+        assert_eq!(cursor.tree(), comb(3));
+    }
+
+    #[test]
+    fn remove_nth_leaf_of_single_leaf_fails() {
+        let cursor = Tree::<i32, i32>::Leaf(1).cursor();
+        let cursor = cursor.remove_nth_leaf(0).unwrap_err();
+        assert_eq!(cursor.tree(), Tree::Leaf(1));
+        assert!(Cursor::<i32, i32>::new().remove_nth_leaf(0).is_err());
+    }
+
+    // ── insert_beside_nth_leaf ─────────────────────────────────
+
+    #[test]
+    fn insert_beside_nth_leaf_left_and_right() {
+        let cursor = comb(3)
+            .cursor()
+            .insert_beside_nth_leaf(1, 99, PathBranch::IsLeft)
+            .unwrap();
+        assert!(!cursor.is_leaf());
+        assert_eq!(cursor.tree().leaves(), vec![&1, &99, &2, &3]);
+
+        let cursor = comb(3)
+            .cursor()
+            .insert_beside_nth_leaf(1, 99, PathBranch::IsRight)
+            .unwrap();
+        assert_eq!(cursor.tree().leaves(), vec![&1, &2, &99, &3]);
+
+        let cursor = comb(3)
+            .cursor()
+            .insert_beside_nth_leaf(2, 99, PathBranch::IsRight)
+            .unwrap();
+        assert_eq!(cursor.tree().leaves(), vec![&1, &2, &3, &99]);
+    }
+
+    #[test]
+    fn insert_beside_nth_leaf_of_single_leaf() {
+        let cursor = Tree::<i32, i32>::Leaf(1)
+            .cursor()
+            .insert_beside_nth_leaf(0, 2, PathBranch::IsLeft)
+            .unwrap();
+        assert!(cursor.is_top());
+        assert_eq!(cursor.tree().leaves(), vec![&2, &1]);
+    }
+
+    #[test]
+    fn insert_beside_nth_leaf_out_of_range_is_unchanged() {
+        let cursor = comb(3).cursor().go_right().unwrap();
+        let cursor = cursor
+            .insert_beside_nth_leaf(2, 99, PathBranch::IsLeft)
+            .unwrap_err();
+        assert!(cursor.is_right());
+        assert_eq!(cursor.tree(), comb(3));
+        assert!(Cursor::<i32, i32>::new()
+            .insert_beside_nth_leaf(0, 1, PathBranch::IsRight)
+            .is_err());
+    }
+
+    // ── go_to_root ─────────────────────────────────────────────
+
+    #[test]
+    fn go_to_root_from_deep_position() {
+        let cursor = comb(5).cursor().go_to_nth_leaf(4).unwrap();
+        assert!(cursor.is_leaf());
+        assert_eq!(cursor.path_to_root().count(), 4);
+
+        let cursor = cursor.go_to_root();
+        assert!(cursor.is_top());
+        assert_eq!(cursor.subtree(), &comb(5));
+
+        // Navigation continues from the top
+        let cursor = cursor.go_left().unwrap();
+        assert_eq!(cursor.subtree(), &Tree::Leaf(1));
+    }
+
+    #[test]
+    fn go_to_root_at_top_is_unchanged() {
+        let cursor = comb(2).cursor().go_to_root();
+        assert!(cursor.is_top());
+        assert_eq!(cursor.tree(), comb(2));
+        assert!(Cursor::<i32, i32>::new().go_to_root().is_top());
+    }
+
+    // ── go_to_leaf_at_ratio ────────────────────────────────────
+
+    #[test]
+    fn go_to_leaf_at_ratio_three_leaves() {
+        for (ratio, expected) in [(0.0, 1), (0.5, 2), (1.0, 3)] {
+            let mut c = comb(3).cursor().go_to_leaf_at_ratio(ratio).unwrap();
+            assert_eq!(*c.leaf_mut().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn go_to_leaf_at_ratio_clamps_out_of_range() {
+        let mut c = comb(4).cursor().go_to_leaf_at_ratio(-3.0).unwrap();
+        assert_eq!(*c.leaf_mut().unwrap(), 1);
+        let mut c = comb(4).cursor().go_to_leaf_at_ratio(7.5).unwrap();
+        assert_eq!(*c.leaf_mut().unwrap(), 4);
+    }
+
+    #[test]
+    fn go_to_leaf_at_ratio_on_empty_tree_fails() {
+        let c: Cursor<i32, ()> = Cursor::new();
+        assert!(c.go_to_leaf_at_ratio(0.5).is_err());
+    }
+
     // ── tree() reconstructs ────────────────────────────────────
 
     #[test]
@@ -8,6 +8,14 @@
 //! client and server instances that are built from different versions
 //! of this code; in this way the client and server can more gracefully
 //! manage unknown enum variants.
+//!
+//! varbincode is positional, so the fields of an existing struct
+//! cannot be changed without breaking the peer.  A field may however
+//! be appended to the end of a struct if it is annotated with
+//! `#[serde(default, deserialize_with = "trailing_default")]`: an older
+//! peer ignores the extra bytes, and we decode a payload from an older
+//! peer that ends before the field as the default.
+//! Use `assert_forward_compatible` in the tests to prove it.
 #![allow(dead_code)]
 #![allow(clippy::range_plus_one)]
 
@@ -35,12 +43,15 @@ use smol::io::AsyncWriteExt;
 #[cfg(feature = "async-smol")]
 use smol::prelude::*;
 
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::future::Future;
 use std::io::Cursor;
 use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use termwiz::hyperlink::Hyperlink;
 use termwiz::image::{ImageData, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
@@ -67,18 +78,53 @@ fn encoded_length(value: u64) -> usize {
 
 const COMPRESSED_MASK: u64 = 1 << 63;
 
+/// When set in the length, the frame carries an additional frame
+/// sequence number following the ident.  Unlike the serial, which
+/// correlates responses with requests, this is a per-connection
+/// counter that allows the receiver to detect dropped or reordered
+/// frames.  Peers that don't send it are unaffected.
+const SEQUENCED_MASK: u64 = 1 << 62;
+
+/// Splits the length read from the wire into the length proper and
+/// the is_compressed and is_sequenced flags
+fn untag_len(tagged_len: u64) -> (u64, bool, bool) {
+    (
+        tagged_len & !(COMPRESSED_MASK | SEQUENCED_MASK),
+        (tagged_len & COMPRESSED_MASK) != 0,
+        (tagged_len & SEQUENCED_MASK) != 0,
+    )
+}
+
+/// Returns the encoded length of the serial, ident and optional
+/// frame sequence number that precede the data in a frame
+fn frame_fields_len(serial: u64, ident: u64, frame_seq: Option<u64>) -> usize {
+    encoded_length(ident) + encoded_length(serial) + frame_seq.map(encoded_length).unwrap_or(0)
+}
+
 fn encode_raw_as_vec(
     ident: u64,
     serial: u64,
     data: &[u8],
     is_compressed: bool,
 ) -> anyhow::Result<Vec<u8>> {
-    let len = data.len() + encoded_length(ident) + encoded_length(serial);
-    let masked_len = if is_compressed {
-        (len as u64) | COMPRESSED_MASK
-    } else {
-        len as u64
-    };
+    encode_sequenced_raw_as_vec(ident, serial, None, data, is_compressed)
+}
+
+fn encode_sequenced_raw_as_vec(
+    ident: u64,
+    serial: u64,
+    frame_seq: Option<u64>,
+    data: &[u8],
+    is_compressed: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let len = data.len() + frame_fields_len(serial, ident, frame_seq);
+    let mut masked_len = len as u64;
+    if is_compressed {
+        masked_len |= COMPRESSED_MASK;
+    }
+    if frame_seq.is_some() {
+        masked_len |= SEQUENCED_MASK;
+    }
 
     // Double-buffer the data; since we run with nodelay enabled, it is
     // desirable for the write to be a single packet (or at least, for
@@ -88,6 +134,9 @@ fn encode_raw_as_vec(
     leb128::write::unsigned(&mut buffer, masked_len).context("writing pdu len")?;
     leb128::write::unsigned(&mut buffer, serial).context("writing pdu serial")?;
     leb128::write::unsigned(&mut buffer, ident).context("writing pdu ident")?;
+    if let Some(frame_seq) = frame_seq {
+        leb128::write::unsigned(&mut buffer, frame_seq).context("writing pdu frame seq")?;
+    }
     buffer.extend_from_slice(data);
 
     if is_compressed {
@@ -99,11 +148,48 @@ fn encode_raw_as_vec(
     Ok(buffer)
 }
 
+/// The largest frame that encode_raw_small() can produce: a single
+/// byte length followed by up to 127 bytes of serial, ident and data.
+const SMALL_FRAME_SIZE: usize = 128;
+
+/// Fast path for tiny uncompressed frames, such as the Ping/Pong heartbeat,
+/// where the length, serial and ident each fit into a single leb128 byte.
+/// The frame is assembled in a stack buffer rather than a heap allocated
+/// Vec; the bytes are identical to those produced by encode_raw_as_vec().
+/// Returns None if the frame doesn't qualify for the fast path.
+fn encode_raw_small(
+    ident: u64,
+    serial: u64,
+    data: &[u8],
+    is_compressed: bool,
+) -> Option<([u8; SMALL_FRAME_SIZE], usize)> {
+    if is_compressed || ident >= 0x80 || serial >= 0x80 {
+        return None;
+    }
+    let len = data.len() + 2;
+    if len >= 0x80 {
+        return None;
+    }
+
+    let mut buffer = [0u8; SMALL_FRAME_SIZE];
+    buffer[0] = len as u8;
+    buffer[1] = serial as u8;
+    buffer[2] = ident as u8;
+    buffer[3..3 + data.len()].copy_from_slice(data);
+    let size = len + 1;
+
+    metrics::histogram!("pdu.encode.size").record(size as f64);
+
+    Some((buffer, size))
+}
+
 /// Encode a frame.  If the data is compressed, the high bit of the length
 /// is set to indicate that.  The data written out has the format:
-/// tagged_len: leb128  (u64 msb is set if data is compressed)
+/// tagged_len: leb128  (u64 msb is set if data is compressed,
+///                      the next bit if frame_seq is present)
 /// serial: leb128
 /// ident: leb128
+/// frame_seq: leb128  (only if flagged in tagged_len)
 /// data bytes
 fn encode_raw<W: std::io::Write>(
     ident: u64,
@@ -112,11 +198,30 @@ fn encode_raw<W: std::io::Write>(
     is_compressed: bool,
     mut w: W,
 ) -> anyhow::Result<usize> {
+    if let Some((buffer, size)) = encode_raw_small(ident, serial, data, is_compressed) {
+        w.write_all(&buffer[..size])
+            .context("writing pdu data buffer")?;
+        return Ok(size);
+    }
     let buffer = encode_raw_as_vec(ident, serial, data, is_compressed)?;
     w.write_all(&buffer).context("writing pdu data buffer")?;
     Ok(buffer.len())
 }
 
+/// Like `encode_raw`, but the frame also carries `frame_seq`
+fn encode_sequenced_raw<W: std::io::Write>(
+    ident: u64,
+    serial: u64,
+    frame_seq: u64,
+    data: &[u8],
+    is_compressed: bool,
+    mut w: W,
+) -> anyhow::Result<usize> {
+    let buffer = encode_sequenced_raw_as_vec(ident, serial, Some(frame_seq), data, is_compressed)?;
+    w.write_all(&buffer).context("writing pdu data buffer")?;
+    Ok(buffer.len())
+}
+
 async fn encode_raw_async<W: Unpin + AsyncWriteExt>(
     ident: u64,
     serial: u64,
@@ -124,6 +229,12 @@ async fn encode_raw_async<W: Unpin + AsyncWriteExt>(
     is_compressed: bool,
     w: &mut W,
 ) -> anyhow::Result<usize> {
+    if let Some((buffer, size)) = encode_raw_small(ident, serial, data, is_compressed) {
+        w.write_all(&buffer[..size])
+            .await
+            .context("writing pdu data buffer")?;
+        return Ok(size);
+    }
     let buffer = encode_raw_as_vec(ident, serial, data, is_compressed)?;
     w.write_all(&buffer)
         .await
@@ -131,15 +242,58 @@ async fn encode_raw_async<W: Unpin + AsyncWriteExt>(
     Ok(buffer.len())
 }
 
+fn read_timed_out(timeout: Duration) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!("no data received within {timeout:?} while reading PDU"),
+    )
+}
+
+/// Await `fut`, failing with `ErrorKind::TimedOut` if `timeout` is
+/// specified and elapses before `fut` completes.
+#[cfg(feature = "async-asupersync")]
+async fn with_read_timeout<T, F>(timeout: Option<Duration>, fut: F) -> std::io::Result<T>
+where
+    F: Future<Output = std::io::Result<T>>,
+{
+    match timeout {
+        None => fut.await,
+        Some(duration) => {
+            asupersync::time::timeout(asupersync::time::wall_now(), duration, Box::pin(fut))
+                .await
+                .unwrap_or_else(|_| Err(read_timed_out(duration)))
+        }
+    }
+}
+
+/// Await `fut`, failing with `ErrorKind::TimedOut` if `timeout` is
+/// specified and elapses before `fut` completes.
+#[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+async fn with_read_timeout<T, F>(timeout: Option<Duration>, fut: F) -> std::io::Result<T>
+where
+    F: Future<Output = std::io::Result<T>>,
+{
+    match timeout {
+        None => fut.await,
+        Some(duration) => {
+            smol::future::or(fut, async move {
+                smol::Timer::after(duration).await;
+                Err(read_timed_out(duration))
+            })
+            .await
+        }
+    }
+}
+
 /// Read a single leb128 encoded value from the stream
-async fn read_u64_async<R>(r: &mut R) -> anyhow::Result<u64>
+async fn read_u64_async<R>(r: &mut R, read_timeout: Option<Duration>) -> anyhow::Result<u64>
 where
     R: Unpin + AsyncRead + std::fmt::Debug,
 {
     let mut buf = vec![];
     loop {
         let mut byte = [0u8];
-        if let Err(err) = r.read_exact(&mut byte).await {
+        if let Err(err) = with_read_timeout(read_timeout, r.read_exact(&mut byte)).await {
             if err.kind() == std::io::ErrorKind::UnexpectedEof {
                 return Err(std::io::Error::new(
                     std::io::ErrorKind::UnexpectedEof,
@@ -176,25 +330,26 @@ fn read_u64<R: std::io::Read>(mut r: R) -> anyhow::Result<u64> {
 struct Decoded {
     ident: u64,
     serial: u64,
+    frame_seq: Option<u64>,
     data: Vec<u8>,
     is_compressed: bool,
 }
 
 /// Decode a frame.
 /// See encode_raw() for the frame format.
+/// If `read_timeout` is specified, each underlying read must make
+/// progress within that duration or the decode fails with an
+/// `ErrorKind::TimedOut` io error.
 async fn decode_raw_async<R: Unpin + AsyncRead + std::fmt::Debug>(
     r: &mut R,
     max_serial: Option<u64>,
+    read_timeout: Option<Duration>,
 ) -> anyhow::Result<Decoded> {
-    let len = read_u64_async(r)
+    let len = read_u64_async(r, read_timeout)
         .await
         .context("decode_raw_async failed to read PDU length")?;
-    let (len, is_compressed) = if (len & COMPRESSED_MASK) != 0 {
-        (len & !COMPRESSED_MASK, true)
-    } else {
-        (len, false)
-    };
-    let serial = read_u64_async(r)
+    let (len, is_compressed, is_sequenced) = untag_len(len);
+    let serial = read_u64_async(r, read_timeout)
         .await
         .context("decode_raw_async failed to read PDU serial")?;
     if let Some(max_serial) = max_serial {
@@ -206,22 +361,31 @@ async fn decode_raw_async<R: Unpin + AsyncRead + std::fmt::Debug>(
             .into());
         }
     }
-    let ident = read_u64_async(r)
+    let ident = read_u64_async(r, read_timeout)
         .await
         .context("decode_raw_async failed to read PDU ident")?;
-    let data_len =
-        match (len as usize).overflowing_sub(encoded_length(ident) + encoded_length(serial)) {
-            (_, true) => {
-                return Err(CorruptResponse(format!(
-                    "decode_raw_async: sizes don't make sense: \
-                    len:{len} serial:{serial} (enc={}) ident:{ident} (enc={})",
-                    encoded_length(serial),
-                    encoded_length(ident)
-                ))
-                .into());
-            }
-            (data_len, false) => data_len,
-        };
+    let frame_seq = if is_sequenced {
+        Some(
+            read_u64_async(r, read_timeout)
+                .await
+                .context("decode_raw_async failed to read PDU frame seq")?,
+        )
+    } else {
+        None
+    };
+    let usize_len = frame_len(len, "decode_raw_async")?;
+    let data_len = match usize_len.overflowing_sub(frame_fields_len(serial, ident, frame_seq)) {
+        (_, true) => {
+            return Err(CorruptResponse(format!(
+                "decode_raw_async: sizes don't make sense: \
+                len:{len} serial:{serial} (enc={}) ident:{ident} (enc={})",
+                encoded_length(serial),
+                encoded_length(ident)
+            ))
+            .into());
+        }
+        (data_len, false) => data_len,
+    };
 
     if is_compressed {
         metrics::histogram!("pdu.decode.compressed.size").record(data_len as f64);
@@ -230,46 +394,66 @@ async fn decode_raw_async<R: Unpin + AsyncRead + std::fmt::Debug>(
     }
 
     let mut data = vec![0u8; data_len];
-    r.read_exact(&mut data).await.with_context(|| {
-        format!(
-            "decode_raw_async failed to read {} bytes of data \
-            for PDU of length {} with serial={} ident={}",
-            data_len, len, serial, ident
-        )
-    })?;
+    with_read_timeout(read_timeout, r.read_exact(&mut data))
+        .await
+        .with_context(|| {
+            format!(
+                "decode_raw_async failed to read {} bytes of data \
+                for PDU of length {} with serial={} ident={}",
+                data_len, len, serial, ident
+            )
+        })?;
     Ok(Decoded {
         ident,
         serial,
+        frame_seq,
         data,
         is_compressed,
     })
 }
 
+/// Convert a frame length read from the wire into a `usize`.
+/// On targets where `usize` is narrower than 64 bits a plausible
+/// looking length may not fit, and a plain `as usize` cast would
+/// silently truncate it into a bogus, smaller frame.
+fn frame_len(len: u64, context: &str) -> Result<usize, CorruptResponse> {
+    if len > usize::MAX as u64 {
+        return Err(CorruptResponse(format!(
+            "{}: PDU length {} exceeds the maximum of {} on this target",
+            context,
+            len,
+            usize::MAX
+        )));
+    }
+    Ok(len as usize)
+}
+
 /// Decode a frame.
 /// See encode_raw() for the frame format.
 fn decode_raw<R: std::io::Read>(mut r: R) -> anyhow::Result<Decoded> {
     let len = read_u64(r.by_ref()).context("reading PDU length")?;
-    let (len, is_compressed) = if (len & COMPRESSED_MASK) != 0 {
-        (len & !COMPRESSED_MASK, true)
-    } else {
-        (len, false)
-    };
+    let (len, is_compressed, is_sequenced) = untag_len(len);
     let serial = read_u64(r.by_ref()).context("reading PDU serial")?;
     let ident = read_u64(r.by_ref()).context("reading PDU ident")?;
-    let data_len =
-        match (len as usize).overflowing_sub(encoded_length(ident) + encoded_length(serial)) {
-            (_, true) => {
-                anyhow::bail!(
-                    "sizes don't make sense: len:{} serial:{} (enc={}) ident:{} (enc={})",
-                    len,
-                    serial,
-                    encoded_length(serial),
-                    ident,
-                    encoded_length(ident)
-                );
-            }
-            (data_len, false) => data_len,
-        };
+    let frame_seq = if is_sequenced {
+        Some(read_u64(r.by_ref()).context("reading PDU frame seq")?)
+    } else {
+        None
+    };
+    let usize_len = frame_len(len, "decode_raw")?;
+    let data_len = match usize_len.overflowing_sub(frame_fields_len(serial, ident, frame_seq)) {
+        (_, true) => {
+            anyhow::bail!(
+                "sizes don't make sense: len:{} serial:{} (enc={}) ident:{} (enc={})",
+                len,
+                serial,
+                encoded_length(serial),
+                ident,
+                encoded_length(ident)
+            );
+        }
+        (data_len, false) => data_len,
+    };
 
     if is_compressed {
         metrics::histogram!("pdu.decode.compressed.size").record(data_len as f64);
@@ -287,20 +471,115 @@ fn decode_raw<R: std::io::Read>(mut r: R) -> anyhow::Result<Decoded> {
     Ok(Decoded {
         ident,
         serial,
+        frame_seq,
         data,
         is_compressed,
     })
 }
 
+/// The header of a frame, as parsed by `peek_frame_header`.
+/// See encode_raw() for the frame format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// The length exactly as it appears on the wire; the msb is set
+    /// if the data is compressed.
+    pub tagged_len: u64,
+    pub serial: u64,
+    pub ident: u64,
+    /// The per-connection frame sequence number, if the sender
+    /// included one.
+    pub frame_seq: Option<u64>,
+    pub is_compressed: bool,
+    /// The number of bytes occupied by the header itself; the data
+    /// starts at this offset into the frame.
+    pub header_len: usize,
+}
+
+impl FrameHeader {
+    /// Returns the number of data bytes that follow the header
+    pub fn data_len(&self) -> usize {
+        let (len, _, _) = untag_len(self.tagged_len);
+        (len as usize).saturating_sub(frame_fields_len(self.serial, self.ident, self.frame_seq))
+    }
+}
+
+/// Parse the header of the frame at the start of `buf` without
+/// consuming or copying anything, and without requiring the data
+/// to be present.  This allows a transport to slice the data of
+/// the frame directly out of its receive buffer.
+/// Returns `Ok(None)` if `buf` doesn't yet hold the complete header.
+pub fn peek_frame_header(buf: &[u8]) -> anyhow::Result<Option<FrameHeader>> {
+    let mut cursor = buf;
+    let mut read = || match leb128::read::unsigned(&mut cursor) {
+        Ok(n) => Ok(Some(n)),
+        Err(leb128::read::Error::IoError(_)) => Ok(None),
+        Err(leb128::read::Error::Overflow) => anyhow::bail!("leb128 is too large"),
+    };
+
+    let tagged_len = match read()? {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+    let serial = match read()? {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+    let ident = match read()? {
+        Some(n) => n,
+        None => return Ok(None),
+    };
+    let (len, is_compressed, is_sequenced) = untag_len(tagged_len);
+    let frame_seq = if is_sequenced {
+        match read()? {
+            Some(n) => Some(n),
+            None => return Ok(None),
+        }
+    } else {
+        None
+    };
+    let header_len = buf.len() - cursor.len();
+
+    let len = frame_len(len, "peek_frame_header")?;
+    if len < frame_fields_len(serial, ident, frame_seq) {
+        return Err(CorruptResponse(format!(
+            "peek_frame_header: sizes don't make sense: \
+            len:{len} serial:{serial} (enc={}) ident:{ident} (enc={})",
+            encoded_length(serial),
+            encoded_length(ident)
+        ))
+        .into());
+    }
+
+    Ok(Some(FrameHeader {
+        tagged_len,
+        serial,
+        ident,
+        frame_seq,
+        is_compressed,
+        header_len,
+    }))
+}
+
 #[derive(Debug, PartialEq)]
 pub struct DecodedPdu {
     pub serial: u64,
+    /// The frame sequence number, if the sender used
+    /// `Pdu::encode_sequenced`.  The caller is responsible for
+    /// checking that these increase by one from frame to frame.
+    pub frame_seq: Option<u64>,
     pub pdu: Pdu,
 }
 
 /// If the serialized size is larger than this, then we'll consider compressing it
 const COMPRESS_THRESH: usize = 32;
 
+/// The ident of the envelope frame produced by
+/// `Pdu::encode_stream_compressed`.  Its payload is a single zstd
+/// stream holding a sequence of complete, uncompressed frames.
+/// This is deliberately far away from the idents used by `Pdu`
+/// variants so that the two can't collide.
+const STREAM_ENVELOPE_IDENT: u64 = 0x3fff;
+
 /// Wire compression policy for PDU encoding.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompressionMode {
@@ -312,28 +591,119 @@ pub enum CompressionMode {
     Never,
 }
 
+#[cfg(test)]
 fn serialize<T: serde::Serialize>(t: &T) -> Result<(Vec<u8>, bool), Error> {
-    serialize_with_mode(t, CompressionMode::Auto)
+    serialize_with_mode(t, CompressionMode::Auto, "??")
+}
+
+/// Returns the size of the compressed payload relative to its
+/// uncompressed size; smaller is better.
+fn compression_ratio(compressed_len: usize, uncompressed_len: usize) -> f64 {
+    if uncompressed_len == 0 {
+        return 1.0;
+    }
+    compressed_len as f64 / uncompressed_len as f64
+}
+
+/// Scratch buffers retained beyond this capacity are released after
+/// use so that one huge PDU doesn't pin that memory for the life of
+/// the thread.
+const SCRATCH_RETAIN_LIMIT: usize = 1024 * 1024;
+
+thread_local! {
+    /// Reusable (uncompressed, compressed) scratch buffers for
+    /// `SerializeScratch`, saving a pair of allocations per PDU.
+    static SERIALIZE_SCRATCH: Cell<(Vec<u8>, Vec<u8>)> =
+        const { Cell::new((Vec::new(), Vec::new())) };
+}
+
+/// The thread's scratch buffers, taken from `SERIALIZE_SCRATCH` for
+/// the duration of an encode and handed back when dropped.  They are
+/// owned rather than borrowed so that they can be held across an
+/// `.await`; a re-entrant encode simply starts with empty buffers.
+struct SerializeScratch {
+    uncompressed: Vec<u8>,
+    compressed: Vec<u8>,
+}
+
+impl SerializeScratch {
+    fn take() -> Self {
+        let (uncompressed, compressed) = SERIALIZE_SCRATCH.with(Cell::take);
+        Self {
+            uncompressed,
+            compressed,
+        }
+    }
+
+    /// Serializes `t`, compressing it according to `compression_mode`,
+    /// and returns whichever encoding wins along with whether it is
+    /// compressed.  `pdu_name` labels the compression ratio metric.
+    fn serialize<T: serde::Serialize>(
+        &mut self,
+        t: &T,
+        compression_mode: CompressionMode,
+        pdu_name: &'static str,
+    ) -> Result<(&[u8], bool), Error> {
+        serialize_into(
+            t,
+            compression_mode,
+            pdu_name,
+            &mut self.uncompressed,
+            &mut self.compressed,
+        )
+    }
+}
+
+impl Drop for SerializeScratch {
+    fn drop(&mut self) {
+        let mut uncompressed = std::mem::take(&mut self.uncompressed);
+        let mut compressed = std::mem::take(&mut self.compressed);
+        for buf in [&mut uncompressed, &mut compressed] {
+            if buf.capacity() > SCRATCH_RETAIN_LIMIT {
+                *buf = Vec::new();
+            }
+        }
+        // The thread-local is already gone if we're dropped while the
+        // thread is exiting, in which case the buffers are just freed.
+        let _ = SERIALIZE_SCRATCH.try_with(|scratch| scratch.set((uncompressed, compressed)));
+    }
 }
 
+/// Serializes `t` into an owned buffer; see `SerializeScratch::serialize`.
+#[cfg(test)]
 fn serialize_with_mode<T: serde::Serialize>(
     t: &T,
     compression_mode: CompressionMode,
+    pdu_name: &'static str,
 ) -> Result<(Vec<u8>, bool), Error> {
-    let mut uncompressed = Vec::new();
-    let mut encode = varbincode::Serializer::new(&mut uncompressed);
+    let mut scratch = SerializeScratch::take();
+    let (data, is_compressed) = scratch.serialize(t, compression_mode, pdu_name)?;
+    Ok((data.to_vec(), is_compressed))
+}
+
+/// Does the work of `SerializeScratch::serialize` using the provided
+/// buffers, returning a view of whichever of them wins.
+fn serialize_into<'a, T: serde::Serialize>(
+    t: &T,
+    compression_mode: CompressionMode,
+    pdu_name: &'static str,
+    uncompressed: &'a mut Vec<u8>,
+    compressed: &'a mut Vec<u8>,
+) -> Result<(&'a [u8], bool), Error> {
+    uncompressed.clear();
+    let mut encode = varbincode::Serializer::new(&mut *uncompressed);
     t.serialize(&mut encode)?;
 
     if compression_mode == CompressionMode::Never {
-        return Ok((uncompressed, false));
+        return Ok((uncompressed.as_slice(), false));
     }
 
     if compression_mode == CompressionMode::Auto && uncompressed.len() <= COMPRESS_THRESH {
-        return Ok((uncompressed, false));
+        return Ok((uncompressed.as_slice(), false));
     }
     // It's a little heavy; let's try compressing it
-    let mut compressed = Vec::new();
-    let mut compress = zstd::Encoder::new(&mut compressed, zstd::DEFAULT_COMPRESSION_LEVEL)?;
+    compressed.clear();
+    let mut compress = zstd::Encoder::new(&mut *compressed, zstd::DEFAULT_COMPRESSION_LEVEL)?;
     let mut encode = varbincode::Serializer::new(&mut compress);
     t.serialize(&mut encode)?;
     compress.finish()?;
@@ -344,34 +714,246 @@ fn serialize_with_mode<T: serde::Serialize>(
         uncompressed.len()
     );
 
-    if compression_mode == CompressionMode::Always {
-        return Ok((compressed, true));
+    if compression_mode == CompressionMode::Always || compressed.len() < uncompressed.len() {
+        metrics::histogram!("pdu.compress.ratio", "pdu" => pdu_name)
+            .record(compression_ratio(compressed.len(), uncompressed.len()));
+        Ok((compressed.as_slice(), true))
+    } else {
+        Ok((uncompressed.as_slice(), false))
+    }
+}
+
+/// The default limit on the size that a compressed PDU may inflate to.
+/// The compressed frame itself can be tiny, so without a cap a
+/// malicious peer could make us decompress gigabytes of data.
+/// This is far larger than any legitimate PDU that we produce.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+/// A reader that fails, rather than silently truncating, once more
+/// than `limit` bytes have been read from the inner reader.
+struct LimitedReader<R> {
+    inner: R,
+    remaining: u64,
+    limit: u64,
+}
+
+impl<R: std::io::Read> std::io::Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            // Only an error if there really is more data to be had
+            let mut probe = [0u8; 1];
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("decompressed PDU exceeds the limit of {} bytes", self.limit),
+                )),
+            };
+        }
+        let max = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+thread_local! {
+    /// Whether the payload currently being deserialized has been fully
+    /// consumed; consulted by `trailing_default`.
+    static PAYLOAD_AT_EOF: Cell<bool> = Cell::new(false);
+}
+
+/// A reader that looks one byte ahead, so that after each read it can
+/// record in `PAYLOAD_AT_EOF` whether anything remains.
+struct EofLookahead<R> {
+    inner: R,
+    peeked: Option<u8>,
+}
+
+impl<R: std::io::Read> EofLookahead<R> {
+    fn new(inner: R) -> std::io::Result<Self> {
+        let mut reader = Self {
+            inner,
+            peeked: None,
+        };
+        reader.peek()?;
+        Ok(reader)
     }
 
-    if compressed.len() < uncompressed.len() {
-        Ok((compressed, true))
-    } else {
-        Ok((uncompressed, false))
+    fn peek(&mut self) -> std::io::Result<()> {
+        let mut probe = [0u8; 1];
+        self.peeked = loop {
+            match self.inner.read(&mut probe) {
+                Ok(0) => break None,
+                Ok(_) => break Some(probe[0]),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        };
+        PAYLOAD_AT_EOF.with(|eof| eof.set(self.peeked.is_none()));
+        Ok(())
+    }
+}
+
+impl<R: std::io::Read> std::io::Read for EofLookahead<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let first = match self.peeked.take() {
+            Some(first) => first,
+            None => return Ok(0),
+        };
+        buf[0] = first;
+        let n = 1 + self.inner.read(&mut buf[1..])?;
+        self.peek()?;
+        Ok(n)
+    }
+}
+
+/// For use with `#[serde(deserialize_with)]` on a field appended to an
+/// existing struct; see the crate docs.  If the payload has already
+/// been fully consumed when the field is reached, the field is absent
+/// and takes its default value.  A payload that ends part way through
+/// the field is still an error.
+fn trailing_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    if PAYLOAD_AT_EOF.with(Cell::get) {
+        return Ok(T::default());
+    }
+    T::deserialize(deserializer)
+}
+
+/// Like `trailing_default`, but for a flag that older peers behaved
+/// as though was set, so that its absence means `true`.
+fn trailing_true<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    if PAYLOAD_AT_EOF.with(Cell::get) {
+        return Ok(true);
     }
+    bool::deserialize(deserializer)
+}
+
+fn default_true() -> bool {
+    true
 }
 
 fn deserialize<T: serde::de::DeserializeOwned, R: std::io::Read>(
-    mut r: R,
+    r: R,
     is_compressed: bool,
 ) -> Result<T, Error> {
-    if is_compressed {
-        let mut decompress = zstd::Decoder::new(r)?;
-        let mut decode = varbincode::Deserializer::new(&mut decompress);
-        serde::Deserialize::deserialize(&mut decode).map_err(Into::into)
-    } else {
-        let mut decode = varbincode::Deserializer::new(&mut r);
-        serde::Deserialize::deserialize(&mut decode).map_err(Into::into)
+    deserialize_with_limit(r, is_compressed, MAX_DECOMPRESSED_SIZE)
+}
+
+thread_local! {
+    /// The limit passed to the `deserialize_with_limit` call that is
+    /// in progress, so that the PDUs nested in a `Batch` are held to
+    /// it too.
+    static DECOMPRESSION_LIMIT: Cell<u64> = const { Cell::new(MAX_DECOMPRESSED_SIZE) };
+}
+
+/// Deserialize a PDU payload, refusing to inflate a compressed
+/// payload beyond `max_decompressed_size` bytes.
+fn deserialize_with_limit<T: serde::de::DeserializeOwned, R: std::io::Read>(
+    r: R,
+    is_compressed: bool,
+    max_decompressed_size: u64,
+) -> Result<T, Error> {
+    // A Batch decodes its PDUs while its own payload is being
+    // deserialized, so preserve the outer payload's state, and
+    // don't let the inner PDUs raise the outer limit
+    let outer_at_eof = PAYLOAD_AT_EOF.with(Cell::get);
+    let outer_limit = DECOMPRESSION_LIMIT.with(Cell::get);
+    let max_decompressed_size = max_decompressed_size.min(outer_limit);
+    DECOMPRESSION_LIMIT.with(|limit| limit.set(max_decompressed_size));
+
+    let result = (|| -> Result<T, Error> {
+        if is_compressed {
+            let decompress = LimitedReader {
+                inner: zstd::Decoder::new(r)?,
+                remaining: max_decompressed_size,
+                limit: max_decompressed_size,
+            };
+            let mut decompress = EofLookahead::new(decompress)?;
+            let mut decode = varbincode::Deserializer::new(&mut decompress);
+            serde::Deserialize::deserialize(&mut decode).map_err(Into::into)
+        } else {
+            let mut r = EofLookahead::new(r)?;
+            let mut decode = varbincode::Deserializer::new(&mut r);
+            serde::Deserialize::deserialize(&mut decode).map_err(Into::into)
+        }
+    })();
+
+    PAYLOAD_AT_EOF.with(|eof| eof.set(outer_at_eof));
+    DECOMPRESSION_LIMIT.with(|limit| limit.set(outer_limit));
+    result
+}
+
+/// Decompresses the payload of a stream envelope, checking that it
+/// holds a sequence of complete frames, none of which is another
+/// envelope, so that expanding it can neither swallow the bytes that
+/// follow it nor be used to compound the decompression limit.
+fn unpack_stream_envelope(
+    decoded: &Decoded,
+    max_decompressed_size: u64,
+) -> anyhow::Result<Vec<u8>> {
+    if !decoded.is_compressed {
+        return Err(CorruptResponse(
+            "stream envelope frame is not marked as compressed".to_string(),
+        )
+        .into());
+    }
+
+    let mut decompress = LimitedReader {
+        inner: zstd::Decoder::new(decoded.data.as_slice())?,
+        remaining: max_decompressed_size,
+        limit: max_decompressed_size,
+    };
+    let mut frames = vec![];
+    std::io::Read::read_to_end(&mut decompress, &mut frames)
+        .context("decompressing stream envelope")?;
+
+    let mut remain = frames.as_slice();
+    while !remain.is_empty() {
+        let header = peek_frame_header(remain)?
+            .ok_or_else(|| CorruptResponse("truncated frame within stream envelope".to_string()))?;
+        if header.ident == STREAM_ENVELOPE_IDENT {
+            return Err(
+                CorruptResponse("stream envelope nested within another".to_string()).into(),
+            );
+        }
+        let frame_len = header.header_len + header.data_len();
+        if remain.len() < frame_len {
+            return Err(
+                CorruptResponse("truncated frame within stream envelope".to_string()).into(),
+            );
+        }
+        remain = &remain[frame_len..];
     }
+    Ok(frames)
+}
+
+/// Called when a frame's ident doesn't match any known Pdu type,
+/// which usually means that the peer is running a different codec
+/// version, so that the skew shows up in logs and dashboards.
+fn record_unknown_ident(ident: u64) {
+    log::warn!("decoded a PDU with unknown ident {ident}; the peer may be running a newer version");
+    metrics::counter!("pdu.decode.unknown_ident", "ident" => ident.to_string()).increment(1);
 }
 
 macro_rules! pdu {
     ($( $name:ident:$vers:expr),* $(,)?) => {
-        #[derive(PartialEq, Debug)]
+        #[derive(PartialEq)]
         #[allow(clippy::large_enum_variant)]
         pub enum Pdu {
             Invalid{ident: u64},
@@ -380,6 +962,21 @@ macro_rules! pdu {
             ,)*
         }
 
+        impl std::fmt::Debug for Pdu {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+                match self {
+                    Pdu::Invalid{ident} => fmt
+                        .debug_struct("Invalid")
+                        .field("ident", ident)
+                        .field("note", &"unknown ident; the peer may be newer than us")
+                        .finish(),
+                    $(
+                        Pdu::$name(s) => fmt.debug_tuple(stringify!($name)).field(s).finish()
+                    ,)*
+                }
+            }
+        }
+
         impl Pdu {
             pub fn encode<W: std::io::Write>(&self, w: W, serial: u64) -> Result<(), Error> {
                 self.encode_with_mode(w, serial, CompressionMode::Auto)
@@ -395,9 +992,10 @@ macro_rules! pdu {
                     Pdu::Invalid{..} => bail!("attempted to serialize Pdu::Invalid"),
                     $(
                         Pdu::$name(s) => {
+                            let mut scratch = SerializeScratch::take();
                             let (data, is_compressed) =
-                                serialize_with_mode(s, compression_mode)?;
-                            let encoded_size = encode_raw($vers, serial, &data, is_compressed, w)?;
+                                scratch.serialize(s, compression_mode, stringify!($name))?;
+                            let encoded_size = encode_raw($vers, serial, data, is_compressed, w)?;
                             log::debug!("encode {} size={encoded_size}", stringify!($name));
                             metrics::histogram!("pdu.size", "pdu" => stringify!($name)).record(encoded_size as f64);
                             metrics::histogram!("pdu.size.rate", "pdu" => stringify!($name)).record(encoded_size as f64);
@@ -407,6 +1005,34 @@ macro_rules! pdu {
                 }
             }
 
+            /// Like `encode`, but the frame also carries `frame_seq`, a
+            /// per-connection counter that the receiver can use to detect
+            /// dropped or reordered frames.  Requires a peer that
+            /// understands sequenced frames.
+            pub fn encode_sequenced<W: std::io::Write>(
+                &self,
+                w: W,
+                serial: u64,
+                frame_seq: u64,
+            ) -> Result<(), Error> {
+                match self {
+                    Pdu::Invalid{..} => bail!("attempted to serialize Pdu::Invalid"),
+                    $(
+                        Pdu::$name(s) => {
+                            let mut scratch = SerializeScratch::take();
+                            let (data, is_compressed) =
+                                scratch.serialize(s, CompressionMode::Auto, stringify!($name))?;
+                            let encoded_size =
+                                encode_sequenced_raw($vers, serial, frame_seq, data, is_compressed, w)?;
+                            log::debug!("encode_sequenced {} size={encoded_size}", stringify!($name));
+                            metrics::histogram!("pdu.size", "pdu" => stringify!($name)).record(encoded_size as f64);
+                            metrics::histogram!("pdu.size.rate", "pdu" => stringify!($name)).record(encoded_size as f64);
+                            Ok(())
+                        }
+                    ,)*
+                }
+            }
+
             pub async fn encode_async<W: Unpin + AsyncWriteExt>(&self, w: &mut W, serial: u64) -> Result<(), Error> {
                 self.encode_async_with_mode(w, serial, CompressionMode::Auto).await
             }
@@ -421,9 +1047,10 @@ macro_rules! pdu {
                     Pdu::Invalid{..} => bail!("attempted to serialize Pdu::Invalid"),
                     $(
                         Pdu::$name(s) => {
+                            let mut scratch = SerializeScratch::take();
                             let (data, is_compressed) =
-                                serialize_with_mode(s, compression_mode)?;
-                            let encoded_size = encode_raw_async($vers, serial, &data, is_compressed, w).await?;
+                                scratch.serialize(s, compression_mode, stringify!($name))?;
+                            let encoded_size = encode_raw_async($vers, serial, data, is_compressed, w).await?;
                             log::debug!("encode_async {} size={encoded_size}", stringify!($name));
                             metrics::histogram!("pdu.size", "pdu" => stringify!($name)).record(encoded_size as f64);
                             metrics::histogram!("pdu.size.rate", "pdu" => stringify!($name)).record(encoded_size as f64);
@@ -444,8 +1071,35 @@ macro_rules! pdu {
                 }
             }
 
+            /// Returns the wire identifier used to tag this variant
+            /// when it is encoded.  `Invalid` has no wire identity of
+            /// its own and yields `u64::MAX`.
+            pub fn ident(&self) -> u64 {
+                match self {
+                    Pdu::Invalid{..} => u64::MAX,
+                    $(
+                        Pdu::$name(_) => $vers
+                    ,)*
+                }
+            }
+
             pub fn decode<R: std::io::Read>(r: R) -> Result<DecodedPdu, Error> {
+                Self::decode_with_limit(r, MAX_DECOMPRESSED_SIZE)
+            }
+
+            /// Like `decode`, but fails if a compressed payload would
+            /// inflate to more than `max_decompressed_size` bytes.
+            pub fn decode_with_limit<R: std::io::Read>(
+                r: R,
+                max_decompressed_size: u64,
+            ) -> Result<DecodedPdu, Error> {
                 let decoded = decode_raw(r).context("decoding a PDU")?;
+                Self::from_decoded(decoded, max_decompressed_size)
+            }
+
+            /// Deserializes the payload of a frame that has already
+            /// been read off the wire.
+            fn from_decoded(decoded: Decoded, max_decompressed_size: u64) -> Result<DecodedPdu, Error> {
                 match decoded.ident {
                     $(
                         $vers => {
@@ -453,15 +1107,32 @@ macro_rules! pdu {
                             metrics::histogram!("pdu.size.rate", "pdu" => stringify!($name)).record(decoded.data.len() as f64);
                             Ok(DecodedPdu {
                                 serial: decoded.serial,
-                                pdu: Pdu::$name(deserialize(decoded.data.as_slice(), decoded.is_compressed)?)
+                                frame_seq: decoded.frame_seq,
+                                pdu: Pdu::$name(
+                                    deserialize_with_limit(
+                                        decoded.data.as_slice(),
+                                        decoded.is_compressed,
+                                        max_decompressed_size,
+                                    )
+                                        .with_context(|| format!(
+                                            "deserializing {} PDU (ident {})",
+                                            stringify!($name),
+                                            $vers
+                                        ))?
+                                )
                             })
                         }
                     ,)*
+                    STREAM_ENVELOPE_IDENT => {
+                        bail!("a stream envelope must be unpacked with Pdu::decode_frames or Pdu::stream_decode")
+                    }
                     _ => {
+                        record_unknown_ident(decoded.ident);
                         metrics::histogram!("pdu.size", "pdu" => "??").record(decoded.data.len() as f64);
                         metrics::histogram!("pdu.size.rate", "pdu" => "??").record(decoded.data.len() as f64);
                         Ok(DecodedPdu {
                             serial: decoded.serial,
+                            frame_seq: decoded.frame_seq,
                             pdu: Pdu::Invalid{ident:decoded.ident}
                         })
                     }
@@ -473,21 +1144,50 @@ macro_rules! pdu {
                       R: AsyncRead,
                       R: std::fmt::Debug
             {
-                let decoded = decode_raw_async(r, max_serial).await.context("decoding a PDU")?;
+                Self::decode_async_with_timeout(r, max_serial, None).await
+            }
+
+            /// Like `decode_async`, but if `read_timeout` is specified then
+            /// a frame that stalls for longer than that between reads fails
+            /// with an `ErrorKind::TimedOut` io error rather than waiting
+            /// forever on a half-open connection.
+            pub async fn decode_async_with_timeout<R>(
+                r: &mut R,
+                max_serial: Option<u64>,
+                read_timeout: Option<Duration>,
+            ) -> Result<DecodedPdu, Error>
+                where R: std::marker::Unpin,
+                      R: AsyncRead,
+                      R: std::fmt::Debug
+            {
+                let decoded = decode_raw_async(r, max_serial, read_timeout).await.context("decoding a PDU")?;
                 match decoded.ident {
                     $(
                         $vers => {
                             metrics::histogram!("pdu.size", "pdu" => stringify!($name)).record(decoded.data.len() as f64);
                             Ok(DecodedPdu {
                                 serial: decoded.serial,
-                                pdu: Pdu::$name(deserialize(decoded.data.as_slice(), decoded.is_compressed)?)
+                                frame_seq: decoded.frame_seq,
+                                pdu: Pdu::$name(
+                                    deserialize(decoded.data.as_slice(), decoded.is_compressed)
+                                        .with_context(|| format!(
+                                            "deserializing {} PDU (ident {})",
+                                            stringify!($name),
+                                            $vers
+                                        ))?
+                                )
                             })
                         }
                     ,)*
+                    STREAM_ENVELOPE_IDENT => {
+                        bail!("a stream envelope must be unpacked with Pdu::decode_frames or Pdu::stream_decode")
+                    }
                     _ => {
+                        record_unknown_ident(decoded.ident);
                         metrics::histogram!("pdu.size", "pdu" => "??").record(decoded.data.len() as f64);
                         Ok(DecodedPdu {
                             serial: decoded.serial,
+                            frame_seq: decoded.frame_seq,
                             pdu: Pdu::Invalid{ident:decoded.ident}
                         })
                     }
@@ -500,7 +1200,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 45;
+pub const CODEC_VERSION: usize = 78;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -561,6 +1261,43 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    SubscribeEvents: 63,
+    Batch: 64,
+    BatchResponse: 65,
+    GetScrollbackLineCount: 66,
+    GetScrollbackLineCountResponse: 67,
+    ResizeTab: 68,
+    SetClipboardV2: 69,
+    GetPaneFontInfo: 70,
+    GetPaneFontInfoResponse: 71,
+    ListTabs: 72,
+    ListTabsResponse: 73,
+    SendRawKey: 74,
+    GetServerInfo: 75,
+    GetServerInfoResponse: 76,
+    Hello: 77,
+    HelloResponse: 78,
+    NotifyProgress: 79,
+    GetSelectionText: 80,
+    GetSelectionTextResponse: 81,
+    WriteToPaneBulk: 82,
+    AttachWorkspace: 83,
+    DetachWorkspace: 84,
+    GetPaneNeighbors: 85,
+    GetPaneNeighborsResponse: 86,
+    GetLinesChunked: 87,
+    GetLinesChunkResponse: 88,
+    CancelRequest: 89,
+    ReattachPane: 90,
+    GetLogicalLines: 91,
+    GetLogicalLinesResponse: 92,
+    BellRang: 93,
+    PingV2: 94,
+    PongV2: 95,
+    SetPaneWorkingDir: 96,
+    SetPaneInputLocked: 97,
+    GetCursorPosition: 98,
+    GetCursorPositionResponse: 99,
 }
 
 impl Pdu {
@@ -571,26 +1308,321 @@ impl Pdu {
         matches!(
             self,
             Self::WriteToPane(_)
+                | Self::WriteToPaneBulk(_)
                 | Self::SendKeyDown(_)
+                | Self::SendRawKey(_)
                 | Self::SendMouseEvent(_)
                 | Self::SendPaste(_)
                 | Self::Resize(_)
+                | Self::ResizeTab(_)
                 | Self::SetClipboard(_)
+                | Self::SetClipboardV2(_)
                 | Self::SetPaneZoomed(_)
                 | Self::SpawnV2(_)
+                | Self::SetPaneWorkingDir(_)
+                | Self::SetPaneInputLocked(_)
         )
     }
 
-    pub fn stream_decode(buffer: &mut Vec<u8>) -> anyhow::Result<Option<DecodedPdu>> {
-        let mut cursor = Cursor::new(buffer.as_slice());
-        match Self::decode(&mut cursor) {
-            Ok(decoded) => {
-                let consumed = cursor.position() as usize;
-                let remain = buffer.len() - consumed;
-                // Remove `consumed` bytes from the start of the vec.
-                // This is safe because the vec is just bytes and we are
-                // constrained the offsets accordingly.
-                unsafe {
+    /// Returns true if this type of Pdu is sent in reply to a request.
+    /// `GetPaneRenderChangesResponse` is also pushed by the server
+    /// without a request, so it is both a response and a notification.
+    /// The match is deliberately exhaustive so that each new Pdu type
+    /// has to be classified.
+    pub fn is_response(&self) -> bool {
+        match self {
+            Self::ErrorResponse(_)
+            | Self::Pong(_)
+            | Self::ListPanesResponse(_)
+            | Self::SpawnResponse(_)
+            | Self::UnitResponse(_)
+            | Self::GetLinesResponse(_)
+            | Self::GetPaneRenderChangesResponse(_)
+            | Self::GetCodecVersionResponse(_)
+            | Self::GetTlsCredsResponse(_)
+            | Self::LivenessResponse(_)
+            | Self::SearchScrollbackResponse(_)
+            | Self::GetClientListResponse(_)
+            | Self::GetImageCellResponse(_)
+            | Self::MovePaneToNewTabResponse(_)
+            | Self::GetPaneRenderableDimensionsResponse(_)
+            | Self::GetPaneDirectionResponse(_)
+            | Self::BatchResponse(_)
+            | Self::GetScrollbackLineCountResponse(_)
+            | Self::GetPaneFontInfoResponse(_)
+            | Self::ListTabsResponse(_)
+            | Self::GetServerInfoResponse(_)
+            | Self::HelloResponse(_)
+            | Self::GetSelectionTextResponse(_)
+            | Self::GetPaneNeighborsResponse(_)
+            | Self::GetLinesChunkResponse(_)
+            | Self::GetLogicalLinesResponse(_)
+            | Self::PongV2(_)
+            | Self::GetCursorPositionResponse(_) => true,
+            Self::Invalid { .. }
+            | Self::Ping(_)
+            | Self::ListPanes(_)
+            | Self::WriteToPane(_)
+            | Self::SendKeyDown(_)
+            | Self::SendMouseEvent(_)
+            | Self::SendPaste(_)
+            | Self::Resize(_)
+            | Self::SetClipboard(_)
+            | Self::GetLines(_)
+            | Self::GetPaneRenderChanges(_)
+            | Self::GetCodecVersion(_)
+            | Self::GetTlsCreds(_)
+            | Self::SearchScrollbackRequest(_)
+            | Self::SetPaneZoomed(_)
+            | Self::SplitPane(_)
+            | Self::KillPane(_)
+            | Self::SpawnV2(_)
+            | Self::PaneRemoved(_)
+            | Self::SetPalette(_)
+            | Self::NotifyAlert(_)
+            | Self::SetClientId(_)
+            | Self::GetClientList(_)
+            | Self::SetWindowWorkspace(_)
+            | Self::WindowWorkspaceChanged(_)
+            | Self::SetFocusedPane(_)
+            | Self::GetImageCell(_)
+            | Self::MovePaneToNewTab(_)
+            | Self::ActivatePaneDirection(_)
+            | Self::GetPaneRenderableDimensions(_)
+            | Self::PaneFocused(_)
+            | Self::TabResized(_)
+            | Self::TabAddedToWindow(_)
+            | Self::TabTitleChanged(_)
+            | Self::WindowTitleChanged(_)
+            | Self::RenameWorkspace(_)
+            | Self::EraseScrollbackRequest(_)
+            | Self::GetPaneDirection(_)
+            | Self::AdjustPaneSize(_)
+            | Self::SubscribeEvents(_)
+            | Self::Batch(_)
+            | Self::GetScrollbackLineCount(_)
+            | Self::ResizeTab(_)
+            | Self::SetClipboardV2(_)
+            | Self::GetPaneFontInfo(_)
+            | Self::ListTabs(_)
+            | Self::SendRawKey(_)
+            | Self::GetServerInfo(_)
+            | Self::Hello(_)
+            | Self::NotifyProgress(_)
+            | Self::GetSelectionText(_)
+            | Self::WriteToPaneBulk(_)
+            | Self::AttachWorkspace(_)
+            | Self::DetachWorkspace(_)
+            | Self::GetPaneNeighbors(_)
+            | Self::GetLinesChunked(_)
+            | Self::CancelRequest(_)
+            | Self::ReattachPane(_)
+            | Self::GetLogicalLines(_)
+            | Self::BellRang(_)
+            | Self::PingV2(_)
+            | Self::SetPaneWorkingDir(_)
+            | Self::SetPaneInputLocked(_)
+            | Self::GetCursorPosition(_) => false,
+        }
+    }
+
+    /// Returns true if this type of Pdu may be pushed by the server
+    /// without a corresponding request.
+    /// The match is deliberately exhaustive so that each new Pdu type
+    /// has to be classified.
+    pub fn is_notification(&self) -> bool {
+        match self {
+            Self::GetPaneRenderChangesResponse(_)
+            | Self::SetClipboard(_)
+            | Self::SetClipboardV2(_)
+            | Self::SetPalette(_)
+            | Self::NotifyAlert(_)
+            | Self::PaneRemoved(_)
+            | Self::PaneFocused(_)
+            | Self::TabResized(_)
+            | Self::TabAddedToWindow(_)
+            | Self::TabTitleChanged(_)
+            | Self::WindowTitleChanged(_)
+            | Self::WindowWorkspaceChanged(_)
+            | Self::NotifyProgress(_)
+            | Self::BellRang(_) => true,
+            Self::Invalid { .. }
+            | Self::ErrorResponse(_)
+            | Self::Ping(_)
+            | Self::Pong(_)
+            | Self::ListPanes(_)
+            | Self::ListPanesResponse(_)
+            | Self::SpawnResponse(_)
+            | Self::WriteToPane(_)
+            | Self::UnitResponse(_)
+            | Self::SendKeyDown(_)
+            | Self::SendMouseEvent(_)
+            | Self::SendPaste(_)
+            | Self::Resize(_)
+            | Self::GetLines(_)
+            | Self::GetLinesResponse(_)
+            | Self::GetPaneRenderChanges(_)
+            | Self::GetCodecVersion(_)
+            | Self::GetCodecVersionResponse(_)
+            | Self::GetTlsCreds(_)
+            | Self::GetTlsCredsResponse(_)
+            | Self::LivenessResponse(_)
+            | Self::SearchScrollbackRequest(_)
+            | Self::SearchScrollbackResponse(_)
+            | Self::SetPaneZoomed(_)
+            | Self::SplitPane(_)
+            | Self::KillPane(_)
+            | Self::SpawnV2(_)
+            | Self::SetClientId(_)
+            | Self::GetClientList(_)
+            | Self::GetClientListResponse(_)
+            | Self::SetWindowWorkspace(_)
+            | Self::SetFocusedPane(_)
+            | Self::GetImageCell(_)
+            | Self::GetImageCellResponse(_)
+            | Self::MovePaneToNewTab(_)
+            | Self::MovePaneToNewTabResponse(_)
+            | Self::ActivatePaneDirection(_)
+            | Self::GetPaneRenderableDimensions(_)
+            | Self::GetPaneRenderableDimensionsResponse(_)
+            | Self::RenameWorkspace(_)
+            | Self::EraseScrollbackRequest(_)
+            | Self::GetPaneDirection(_)
+            | Self::GetPaneDirectionResponse(_)
+            | Self::AdjustPaneSize(_)
+            | Self::SubscribeEvents(_)
+            | Self::Batch(_)
+            | Self::BatchResponse(_)
+            | Self::GetScrollbackLineCount(_)
+            | Self::GetScrollbackLineCountResponse(_)
+            | Self::ResizeTab(_)
+            | Self::GetPaneFontInfo(_)
+            | Self::GetPaneFontInfoResponse(_)
+            | Self::ListTabs(_)
+            | Self::ListTabsResponse(_)
+            | Self::SendRawKey(_)
+            | Self::GetServerInfo(_)
+            | Self::GetServerInfoResponse(_)
+            | Self::Hello(_)
+            | Self::HelloResponse(_)
+            | Self::GetSelectionText(_)
+            | Self::GetSelectionTextResponse(_)
+            | Self::WriteToPaneBulk(_)
+            | Self::AttachWorkspace(_)
+            | Self::DetachWorkspace(_)
+            | Self::GetPaneNeighbors(_)
+            | Self::GetPaneNeighborsResponse(_)
+            | Self::GetLinesChunked(_)
+            | Self::GetLinesChunkResponse(_)
+            | Self::CancelRequest(_)
+            | Self::ReattachPane(_)
+            | Self::GetLogicalLines(_)
+            | Self::GetLogicalLinesResponse(_)
+            | Self::PingV2(_)
+            | Self::PongV2(_)
+            | Self::SetPaneWorkingDir(_)
+            | Self::SetPaneInputLocked(_)
+            | Self::GetCursorPosition(_)
+            | Self::GetCursorPositionResponse(_) => false,
+        }
+    }
+
+    /// Encodes `pdus` as a single envelope frame whose payload is one
+    /// zstd stream over all of their frames.  PDUs that are each too
+    /// small for `encode` to bother compressing, such as a burst of
+    /// notifications, compress much better together.
+    /// Only send this to a peer that negotiated
+    /// `FeatureSet::STREAM_COMPRESSION`; it must read with
+    /// `Pdu::decode_frames`, `Pdu::stream_decode` or something built on
+    /// them, as the other decoders fail on an envelope.
+    pub fn encode_stream_compressed<'a, W: std::io::Write>(
+        pdus: impl IntoIterator<Item = (u64, &'a Pdu)>,
+        w: W,
+    ) -> Result<(), Error> {
+        let mut frames = vec![];
+        let mut count = 0;
+        for (serial, pdu) in pdus {
+            pdu.encode_with_mode(&mut frames, serial, CompressionMode::Never)?;
+            count += 1;
+        }
+        let compressed = zstd::encode_all(frames.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)?;
+        metrics::histogram!("pdu.compress.ratio", "pdu" => "StreamEnvelope")
+            .record(compression_ratio(compressed.len(), frames.len()));
+        let encoded_size = encode_raw(STREAM_ENVELOPE_IDENT, 0, &compressed, true, w)?;
+        log::debug!(
+            "encode_stream_compressed {} pdus size={} vs {}",
+            count,
+            encoded_size,
+            frames.len()
+        );
+        Ok(())
+    }
+
+    /// Decodes the next frame from `r`.  If it is an envelope produced
+    /// by `Pdu::encode_stream_compressed` then each of the PDUs that it
+    /// holds are returned in order, otherwise the single PDU that the
+    /// frame holds is returned.
+    pub fn decode_frames<R: std::io::Read>(r: R) -> Result<Vec<DecodedPdu>, Error> {
+        Self::decode_frames_with_limit(r, MAX_DECOMPRESSED_SIZE)
+    }
+
+    /// Like `decode_frames`, but fails if the envelope, or any of the
+    /// PDUs in it, would inflate to more than `max_decompressed_size`
+    /// bytes.
+    pub fn decode_frames_with_limit<R: std::io::Read>(
+        r: R,
+        max_decompressed_size: u64,
+    ) -> Result<Vec<DecodedPdu>, Error> {
+        let decoded = decode_raw(r).context("decoding a PDU")?;
+        if decoded.ident != STREAM_ENVELOPE_IDENT {
+            return Ok(vec![Self::from_decoded(decoded, max_decompressed_size)?]);
+        }
+
+        let frames = unpack_stream_envelope(&decoded, max_decompressed_size)?;
+        let mut cursor = Cursor::new(frames.as_slice());
+        let mut pdus = vec![];
+        while (cursor.position() as usize) < frames.len() {
+            pdus.push(
+                Self::decode_with_limit(&mut cursor, max_decompressed_size)
+                    .context("decoding PDU within stream envelope")?,
+            );
+        }
+        Ok(pdus)
+    }
+
+    /// If `buffer` starts with a complete stream envelope, replaces it
+    /// with the frames that it holds so that they can be decoded one
+    /// at a time.
+    fn expand_stream_envelope(buffer: &mut Vec<u8>) -> anyhow::Result<()> {
+        let header = match peek_frame_header(buffer)? {
+            Some(header) if header.ident == STREAM_ENVELOPE_IDENT => header,
+            _ => return Ok(()),
+        };
+        let frame_len = header.header_len + header.data_len();
+        if buffer.len() < frame_len {
+            return Ok(());
+        }
+        let decoded = decode_raw(&buffer[..frame_len]).context("decoding a PDU")?;
+        let frames = unpack_stream_envelope(&decoded, MAX_DECOMPRESSED_SIZE)?;
+        buffer.splice(..frame_len, frames);
+        Ok(())
+    }
+
+    /// Decodes the next PDU from the start of `buffer`, removing its
+    /// frame from the buffer.  A stream envelope is expanded in place,
+    /// after which its PDUs are returned by this and subsequent calls.
+    /// Returns `Ok(None)` if `buffer` doesn't yet hold a complete frame.
+    pub fn stream_decode(buffer: &mut Vec<u8>) -> anyhow::Result<Option<DecodedPdu>> {
+        Self::expand_stream_envelope(buffer)?;
+        let mut cursor = Cursor::new(buffer.as_slice());
+        match Self::decode(&mut cursor) {
+            Ok(decoded) => {
+                let consumed = cursor.position() as usize;
+                let remain = buffer.len() - consumed;
+                // Remove `consumed` bytes from the start of the vec.
+                // This is safe because the vec is just bytes and we are
+                // constrained the offsets accordingly.
+                unsafe {
                     std::ptr::copy_nonoverlapping(
                         buffer.as_ptr().add(consumed),
                         buffer.as_mut_ptr(),
@@ -616,6 +1648,25 @@ impl Pdu {
         }
     }
 
+    /// Decodes the PDU at the start of `buf`, returning it together
+    /// with the number of bytes its frame occupied so that the caller
+    /// can advance past it.  Unlike `stream_decode`, `buf` is left
+    /// untouched, so it may be borrowed from a larger buffer; as a
+    /// consequence it can't expand a stream envelope, which is an error.
+    /// Returns `Ok(None)` if `buf` doesn't yet hold a complete frame.
+    pub fn decode_from_slice(buf: &[u8]) -> anyhow::Result<Option<(DecodedPdu, usize)>> {
+        let header = match peek_frame_header(buf)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let frame_len = header.header_len + header.data_len();
+        if buf.len() < frame_len {
+            return Ok(None);
+        }
+        let decoded = Self::decode(&buf[..frame_len])?;
+        Ok(Some((decoded, frame_len)))
+    }
+
     pub fn try_read_and_decode<R: std::io::Read>(
         r: &mut R,
         buffer: &mut Vec<u8>,
@@ -650,9 +1701,26 @@ impl Pdu {
     pub fn pane_id(&self) -> Option<PaneId> {
         match self {
             Pdu::GetPaneRenderChangesResponse(GetPaneRenderChangesResponse { pane_id, .. })
+            | Pdu::GetScrollbackLineCountResponse(GetScrollbackLineCountResponse {
+                pane_id, ..
+            })
+            | Pdu::GetPaneFontInfoResponse(GetPaneFontInfoResponse { pane_id, .. })
+            | Pdu::GetSelectionTextResponse(GetSelectionTextResponse { pane_id, .. })
+            | Pdu::GetPaneNeighborsResponse(GetPaneNeighborsResponse { pane_id, .. })
+            | Pdu::GetLinesChunkResponse(GetLinesChunkResponse { pane_id, .. })
+            | Pdu::GetCursorPositionResponse(GetCursorPositionResponse { pane_id, .. })
+            | Pdu::ReattachPane(ReattachPane { pane_id, .. })
+            | Pdu::SetPaneWorkingDir(SetPaneWorkingDir { pane_id, .. })
+            | Pdu::SetPaneInputLocked(SetPaneInputLocked { pane_id, .. })
+            | Pdu::GetLogicalLinesResponse(GetLogicalLinesResponse { pane_id, .. })
             | Pdu::SetPalette(SetPalette { pane_id, .. })
             | Pdu::NotifyAlert(NotifyAlert { pane_id, .. })
+            | Pdu::NotifyProgress(NotifyProgress { pane_id, .. })
+            | Pdu::BellRang(BellRang { pane_id, .. })
             | Pdu::SetClipboard(SetClipboard { pane_id, .. })
+            | Pdu::SetClipboardV2(SetClipboardV2 { pane_id, .. })
+            | Pdu::SendRawKey(SendRawKey { pane_id, .. })
+            | Pdu::WriteToPaneBulk(WriteToPaneBulk { pane_id, .. })
             | Pdu::PaneFocused(PaneFocused { pane_id })
             | Pdu::PaneRemoved(PaneRemoved { pane_id }) => Some(*pane_id),
             _ => None,
@@ -660,6 +1728,79 @@ impl Pdu {
     }
 }
 
+/// Decodes a stream of PDUs from `reader`, holding on to any partially
+/// received frame between calls so that callers don't have to manage
+/// the accumulation buffer for `Pdu::try_read_and_decode` themselves.
+pub struct PduStreamDecoder<R: std::io::Read> {
+    reader: R,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: std::io::Read> PduStreamDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Reads and decodes the next PDU.
+    /// Returns `Ok(None)` if the reader would block before a complete
+    /// PDU is available, and an `UnexpectedEof` error if the reader
+    /// reaches end of file.
+    pub fn next_pdu(&mut self) -> anyhow::Result<Option<DecodedPdu>> {
+        Pdu::try_read_and_decode(&mut self.reader, &mut self.buffer)
+    }
+
+    /// Returns the number of bytes received but not yet decoded: a
+    /// partially received frame, or what remains of an expanded
+    /// stream envelope
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Yields PDUs until the reader would block or reaches end of file.
+/// End of file on a frame boundary ends the iteration cleanly, while
+/// end of file part way through a frame, or any other error, is
+/// yielded once before the iteration ends.
+impl<R: std::io::Read> Iterator for PduStreamDecoder<R> {
+    type Item = anyhow::Result<DecodedPdu>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_pdu() {
+            Ok(Some(decoded)) => Some(Ok(decoded)),
+            Ok(None) => None,
+            Err(err) => {
+                self.done = true;
+                let clean_eof = self.buffer.is_empty()
+                    && err
+                        .downcast_ref::<std::io::Error>()
+                        .map(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+                        .unwrap_or(false);
+                if clean_eof {
+                    None
+                } else {
+                    Some(Err(err))
+                }
+            }
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct UnitResponse {}
 
@@ -677,6 +1818,117 @@ pub struct GetCodecVersionResponse {
     pub version_string: String,
     pub executable_path: PathBuf,
     pub config_file_path: Option<PathBuf>,
+    /// Identifies the server process; see `server_epoch()`.
+    /// Older servers don't send it, in which case it is 0.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub server_epoch: u64,
+}
+
+/// Returns a value identifying this process, for use as the
+/// `server_epoch` reported to clients.  It is the time at which it
+/// was first called, in nanoseconds since the unix epoch, so it is
+/// fixed for the life of the process and larger after a restart.
+/// A client that sees it change on reconnect is talking to a fresh
+/// server and should discard any pane ids it has cached.
+pub fn server_epoch() -> u64 {
+    static EPOCH: std::sync::OnceLock<u64> = std::sync::OnceLock::new();
+    *EPOCH.get_or_init(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0)
+            .max(1)
+    })
+}
+
+bitflags::bitflags! {
+    /// Optional protocol capabilities.  Each peer advertises the
+    /// features it implements in `Hello`/`HelloResponse`, and the
+    /// connection uses only those that both sides have in common.
+    #[derive(Default, Deserialize, Serialize)]
+    pub struct FeatureSet: u64 {
+        /// Payloads may be zstd compressed
+        const COMPRESSION_ZSTD = 1;
+        /// Frames may carry a frame sequence number;
+        /// see `Pdu::encode_sequenced`
+        const FRAME_SEQUENCE = 2;
+        /// `SubscribeEvents` is honored
+        const EVENT_SUBSCRIPTIONS = 4;
+        /// `Batch` requests are honored
+        const BATCH = 8;
+        /// Frames may be grouped into a stream envelope;
+        /// see `Pdu::encode_stream_compressed`
+        const STREAM_COMPRESSION = 16;
+    }
+}
+
+impl FeatureSet {
+    /// Returns the features that may be used on a connection where
+    /// we advertised `self` and the peer advertised `peer`.
+    /// Bits that are unknown to this version of the codec are
+    /// dropped, even if both peers happen to set them.
+    pub fn negotiate(self, peer: Self) -> Self {
+        self & peer & Self::all()
+    }
+}
+
+/// Sent by a client as the first request on a connection to
+/// declare its codec version and the optional features it supports.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct Hello {
+    pub codec_vers: usize,
+    pub features: FeatureSet,
+}
+
+/// The server's reply to `Hello`, declaring its own codec version
+/// and supported features, along with the liveness parameters that
+/// both sides should use for the connection.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct HelloResponse {
+    pub codec_vers: usize,
+    /// The features that the server implements.  This is empty by
+    /// default; a server must list the features that it really
+    /// supports rather than everything this codec knows about.
+    pub features: FeatureSet,
+    /// How often the client should send a `Ping` on an otherwise
+    /// idle connection
+    pub heartbeat_interval_secs: u32,
+    /// How long either side may go without hearing from the other
+    /// before considering the connection dead
+    pub heartbeat_timeout_secs: u32,
+    /// Identifies the server process; see `server_epoch()`
+    pub server_epoch: u64,
+}
+
+impl HelloResponse {
+    pub const DEFAULT_HEARTBEAT_INTERVAL_SECS: u32 = 30;
+    pub const DEFAULT_HEARTBEAT_TIMEOUT_SECS: u32 = 90;
+}
+
+impl Default for HelloResponse {
+    fn default() -> Self {
+        Self {
+            codec_vers: CODEC_VERSION,
+            features: FeatureSet::empty(),
+            heartbeat_interval_secs: Self::DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_timeout_secs: Self::DEFAULT_HEARTBEAT_TIMEOUT_SECS,
+            server_epoch: server_epoch(),
+        }
+    }
+}
+
+/// Requests a one-shot summary of server health, so that status
+/// tools needn't compose `GetCodecVersion`, `GetClientList` etc.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetServerInfo {}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetServerInfoResponse {
+    pub version_string: String,
+    pub codec_vers: usize,
+    pub uptime_secs: u64,
+    pub client_count: usize,
+    pub pane_count: usize,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -684,6 +1936,42 @@ pub struct Ping {}
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct Pong {}
 
+/// Like `Ping`, but carries a token that the server echoes back in
+/// the matching `PongV2`, allowing the client to measure round-trip
+/// time.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PingV2 {
+    pub token: u64,
+}
+
+impl PingV2 {
+    /// Stamps the ping with the current time in milliseconds, as
+    /// for `InputSerial::now`.
+    pub fn now() -> Self {
+        Self {
+            token: InputSerial::now().0,
+        }
+    }
+
+    /// Builds the pong that echoes this ping's token.
+    pub fn reply(&self) -> PongV2 {
+        PongV2 { token: self.token }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PongV2 {
+    pub token: u64,
+}
+
+impl PongV2 {
+    /// Returns the milliseconds elapsed since the matching ping was
+    /// stamped by `PingV2::now`.
+    pub fn elapsed_millis(&self) -> u64 {
+        InputSerial(self.token).elapsed_millis()
+    }
+}
+
 /// Requests a client certificate to authenticate against
 /// the TLS based server
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -708,6 +1996,27 @@ pub struct ListPanesResponse {
     pub window_titles: HashMap<WindowId, String>,
 }
 
+/// Lists just the tabs, without the pane layout that `ListPanes`
+/// returns.  If `window_id` is set, only that window's tabs are
+/// returned, otherwise the tabs of all windows are.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListTabs {
+    pub window_id: Option<WindowId>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct TabInfo {
+    pub tab_id: TabId,
+    pub title: String,
+    pub is_active: bool,
+    pub pane_count: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListTabsResponse {
+    pub tabs: Vec<TabInfo>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SplitPane {
     pub pane_id: PaneId,
@@ -718,6 +2027,11 @@ pub struct SplitPane {
     /// Instead of spawning a command, move the specified
     /// pane into the new split target
     pub move_pane_id: Option<PaneId>,
+    /// Extra environment variables for the spawned process, which
+    /// take precedence over those in `command`.  Empty if the
+    /// environment should be left as is.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub env: HashMap<String, String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -727,6 +2041,36 @@ pub struct MovePaneToNewTab {
     pub workspace_for_new_window: Option<String>,
 }
 
+/// Makes `client_id` the client driving `pane_id`, eg: when a
+/// session is moved over to a newly connected client.
+/// The server replies with a `UnitResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ReattachPane {
+    pub pane_id: PaneId,
+    pub client_id: ClientId,
+}
+
+/// Sets the working directory that the server reports for `pane_id`,
+/// as though the shell had emitted OSC 7, so that it is reflected in
+/// subsequent `GetPaneRenderChangesResponse::working_dir` values.
+/// The server replies with a `UnitResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneWorkingDir {
+    pub pane_id: PaneId,
+    pub cwd: SerdeUrl,
+}
+
+/// Locks or unlocks input to `pane_id`.  While locked, the server
+/// rejects `WriteToPane` and `SendKeyDown` for the pane with an
+/// `ErrorResponse`, so that stray input from automation can't reach
+/// a pane running something dangerous.
+/// The server replies with a `UnitResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneInputLocked {
+    pub pane_id: PaneId,
+    pub locked: bool,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct MovePaneToNewTabResponse {
     pub tab_id: TabId,
@@ -742,6 +2086,11 @@ pub struct SpawnV2 {
     pub command_dir: Option<String>,
     pub size: TerminalSize,
     pub workspace: String,
+    /// Extra environment variables for the spawned process, which
+    /// take precedence over those in `command`.  Empty if the
+    /// environment should be left as is.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub env: HashMap<String, String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -768,6 +2117,26 @@ pub struct WriteToPane {
     pub data: Vec<u8>,
 }
 
+/// Coalesces a burst of writes to a pane, such as automation typing
+/// one character at a time, into a single PDU.  The chunks must be
+/// delivered to the pane in order; see `write_to`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct WriteToPaneBulk {
+    pub pane_id: PaneId,
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl WriteToPaneBulk {
+    /// Writes each chunk to `w` in order, as though each had
+    /// arrived in its own `WriteToPane`.
+    pub fn write_to<W: std::io::Write>(&self, w: &mut W) -> std::io::Result<()> {
+        for chunk in &self.chunks {
+            w.write_all(chunk)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SendPaste {
     pub pane_id: PaneId,
@@ -781,6 +2150,16 @@ pub struct SendKeyDown {
     pub input_serial: InputSerial,
 }
 
+/// Delivers `bytes` to the pane as though they had been typed,
+/// for automation that has a raw key or escape sequence rather
+/// than a structured `KeyEvent`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SendRawKey {
+    pub pane_id: PaneId,
+    pub bytes: Vec<u8>,
+    pub input_serial: InputSerial,
+}
+
 /// InputSerial is used to sequence input requests with output events.
 /// It started life as a monotonic sequence number but evolved into
 /// the number of milliseconds since the unix epoch.
@@ -828,12 +2207,37 @@ pub struct SetClipboard {
     pub selection: ClipboardSelection,
 }
 
+/// Like `SetClipboard`, but carries arbitrary binary data labelled
+/// with its MIME type, such as an image, rather than just text.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetClipboardV2 {
+    pub pane_id: PaneId,
+    pub mime_type: String,
+    pub data: Option<Vec<u8>>,
+    pub selection: ClipboardSelection,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SetWindowWorkspace {
     pub window_id: WindowId,
     pub workspace: String,
 }
 
+/// Attaches the client to a workspace, so that it receives updates
+/// for that workspace's panes.  The server replies with a
+/// `ListPanesResponse` holding only the tabs in `workspace`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct AttachWorkspace {
+    pub workspace: String,
+}
+
+/// Detaches the client from a workspace previously attached with
+/// `AttachWorkspace`.  The server replies with a `UnitResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct DetachWorkspace {
+    pub workspace: String,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct RenameWorkspace {
     pub old_workspace: String,
@@ -855,6 +2259,28 @@ pub struct NotifyAlert {
     pub alert: Alert,
 }
 
+/// Reports the progress of a long running task associated with
+/// a pane, such as a remote build, for the client to render as
+/// a progress bar.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct NotifyProgress {
+    pub pane_id: PaneId,
+    pub label: String,
+    /// How much of the task is complete, from 0.0 to 1.0,
+    /// or `None` if that is unknown and a spinner is more
+    /// appropriate than a bar.
+    pub fraction: Option<f32>,
+}
+
+/// Sent when the bell rings in a pane, so that a client can flash
+/// or show a toast without decoding the `Alert` in a `NotifyAlert`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct BellRang {
+    pub pane_id: PaneId,
+    /// True if the bell is configured as a visual bell
+    pub visual: bool,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct TabAddedToWindow {
     pub tab_id: TabId,
@@ -889,10 +2315,185 @@ pub struct WindowWorkspaceChanged {
     pub workspace: String,
 }
 
+/// Identifies a kind of notification that the server pushes to
+/// clients unprompted.  Each variant is named after the Pdu that
+/// carries it.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum EventKind {
+    GetPaneRenderChangesResponse,
+    SetClipboard,
+    SetPalette,
+    NotifyAlert,
+    PaneRemoved,
+    PaneFocused,
+    TabResized,
+    TabAddedToWindow,
+    TabTitleChanged,
+    WindowTitleChanged,
+    WindowWorkspaceChanged,
+    NotifyProgress,
+    BellRang,
+}
+
+impl EventKind {
+    pub const ALL: &'static [EventKind] = &[
+        EventKind::GetPaneRenderChangesResponse,
+        EventKind::SetClipboard,
+        EventKind::SetPalette,
+        EventKind::NotifyAlert,
+        EventKind::PaneRemoved,
+        EventKind::PaneFocused,
+        EventKind::TabResized,
+        EventKind::TabAddedToWindow,
+        EventKind::TabTitleChanged,
+        EventKind::WindowTitleChanged,
+        EventKind::WindowWorkspaceChanged,
+        EventKind::NotifyProgress,
+        EventKind::BellRang,
+    ];
+
+    /// Returns the kind of notification carried by `pdu`, or `None`
+    /// if it isn't one of the server push notifications.
+    pub fn of(pdu: &Pdu) -> Option<Self> {
+        match pdu {
+            Pdu::GetPaneRenderChangesResponse(_) => Some(Self::GetPaneRenderChangesResponse),
+            Pdu::SetClipboard(_) | Pdu::SetClipboardV2(_) => Some(Self::SetClipboard),
+            Pdu::SetPalette(_) => Some(Self::SetPalette),
+            Pdu::NotifyAlert(_) => Some(Self::NotifyAlert),
+            Pdu::PaneRemoved(_) => Some(Self::PaneRemoved),
+            Pdu::PaneFocused(_) => Some(Self::PaneFocused),
+            Pdu::TabResized(_) => Some(Self::TabResized),
+            Pdu::TabAddedToWindow(_) => Some(Self::TabAddedToWindow),
+            Pdu::TabTitleChanged(_) => Some(Self::TabTitleChanged),
+            Pdu::WindowTitleChanged(_) => Some(Self::WindowTitleChanged),
+            Pdu::WindowWorkspaceChanged(_) => Some(Self::WindowWorkspaceChanged),
+            Pdu::NotifyProgress(_) => Some(Self::NotifyProgress),
+            Pdu::BellRang(_) => Some(Self::BellRang),
+            _ => None,
+        }
+    }
+}
+
+/// Sent by a client to tell the server which push notifications it
+/// wants to receive.  A client that never sends this receives all
+/// of them, which is also what the `Default` subscription asks for.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SubscribeEvents {
+    pub events: Vec<EventKind>,
+}
+
+impl Default for SubscribeEvents {
+    fn default() -> Self {
+        Self {
+            events: EventKind::ALL.to_vec(),
+        }
+    }
+}
+
+impl SubscribeEvents {
+    /// Returns true if `pdu` should be sent to a client holding this
+    /// subscription.  Anything that isn't a push notification, such
+    /// as a response to a request, is always wanted.
+    pub fn wants(&self, pdu: &Pdu) -> bool {
+        match EventKind::of(pdu) {
+            Some(kind) => self.events.contains(&kind),
+            None => true,
+        }
+    }
+}
+
+/// Coalesces several requests into a single round trip.
+/// The server replies with a `BatchResponse` holding one response
+/// per request, in the same order.  Batches cannot be nested.
+#[derive(Deserialize, Serialize, PartialEq, Debug, Default)]
+pub struct Batch {
+    #[serde(with = "nested_pdus")]
+    pub requests: Vec<Pdu>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Default)]
+pub struct BatchResponse {
+    #[serde(with = "nested_pdus")]
+    pub responses: Vec<Pdu>,
+}
+
+/// Pdu itself isn't Serialize, so the PDUs carried by `Batch` and
+/// `BatchResponse` are each stored as a complete encoded frame.
+/// The serial number of those inner frames is unused and always 0;
+/// responses are matched up with requests by their position.
+mod nested_pdus {
+    use super::{peek_frame_header, Batch, BatchResponse, Pdu, DECOMPRESSION_LIMIT};
+    use serde::de::Error as _;
+    use serde::ser::Error as _;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    fn is_batch(ident: u64) -> bool {
+        ident == Pdu::Batch(Batch::default()).ident()
+            || ident == Pdu::BatchResponse(BatchResponse::default()).ident()
+    }
+
+    pub fn serialize<S: Serializer>(pdus: &[Pdu], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut frames = Vec::with_capacity(pdus.len());
+        for pdu in pdus {
+            if is_batch(pdu.ident()) {
+                return Err(S::Error::custom("a Batch cannot contain another batch"));
+            }
+            let mut frame = vec![];
+            pdu.encode(&mut frame, 0)
+                .map_err(|err| S::Error::custom(format!("{:#}", err)))?;
+            frames.push(frame);
+        }
+        frames.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Pdu>, D::Error> {
+        let frames = Vec::<Vec<u8>>::deserialize(deserializer)?;
+        frames
+            .iter()
+            .map(|frame| {
+                // Check the ident before decoding, so that a hostile
+                // peer can't make us recurse through nested batches
+                let header = peek_frame_header(frame)
+                    .map_err(|err| D::Error::custom(format!("{:#}", err)))?
+                    .ok_or_else(|| D::Error::custom("truncated PDU in Batch"))?;
+                if is_batch(header.ident) {
+                    return Err(D::Error::custom("a Batch cannot contain another batch"));
+                }
+                // Hold the PDU to the limit of the Batch that carries it
+                let limit = DECOMPRESSION_LIMIT.with(std::cell::Cell::get);
+                Pdu::decode_with_limit(frame.as_slice(), limit)
+                    .map(|decoded| decoded.pdu)
+                    .map_err(|err| D::Error::custom(format!("{:#}", err)))
+            })
+            .collect()
+    }
+}
+
+bitflags::bitflags! {
+    /// What a client is able to render or process, declared when it
+    /// registers with `SetClientId`.  The empty set describes a
+    /// legacy client that supports none of these.
+    #[derive(Default, Deserialize, Serialize)]
+    pub struct ClientCapabilities: u64 {
+        /// Can render image cells fetched via `GetImageCell`
+        const IMAGES = 1;
+        /// Can apply the incremental updates carried by
+        /// `GetPaneRenderChangesResponse`
+        const RENDER_DELTAS = 2;
+        /// Understands `SetClipboardV2`
+        const CLIPBOARD_V2 = 4;
+        /// Can render `NotifyProgress` notifications
+        const PROGRESS = 8;
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SetClientId {
     pub client_id: ClientId,
     pub is_proxy: bool,
+    /// Empty when registered by a client that predates capabilities
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub capabilities: ClientCapabilities,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -915,6 +2516,15 @@ pub struct Resize {
     pub size: TerminalSize,
 }
 
+/// Resizes all of the panes in a tab in a single step, avoiding
+/// the intermediate states of sending a `Resize` for each pane.
+/// The server responds with `UnitResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ResizeTab {
+    pub tab_id: TabId,
+    pub size: TerminalSize,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SetPaneZoomed {
     pub containing_tab_id: TabId,
@@ -940,6 +2550,23 @@ pub struct GetPaneDirectionResponse {
     pub pane_id: Option<PaneId>,
 }
 
+/// Requests the neighbors of a pane in all four directions at once,
+/// saving the round trips of a `GetPaneDirection` for each.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneNeighbors {
+    pub pane_id: PaneId,
+}
+
+/// Each field is `None` if there is no pane in that direction
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneNeighborsResponse {
+    pub pane_id: PaneId,
+    pub up: Option<PaneId>,
+    pub down: Option<PaneId>,
+    pub left: Option<PaneId>,
+    pub right: Option<PaneId>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ActivatePaneDirection {
     pub pane_id: PaneId,
@@ -949,6 +2576,39 @@ pub struct ActivatePaneDirection {
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetPaneRenderChanges {
     pub pane_id: PaneId,
+    /// The seqno up to which the client already holds the pane
+    /// content, allowing the server to omit anything that hasn't
+    /// changed since.  `None` asks for the server's usual response.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub since_seqno: Option<SequenceNo>,
+    /// Whether the server should populate `bonus_lines` in its
+    /// response.  Clients that fetch lines themselves can set this
+    /// to false to save bandwidth; `new` defaults it to true, as
+    /// does a request from a client that predates the flag.
+    #[serde(default = "default_true", deserialize_with = "trailing_true")]
+    pub want_bonus_lines: bool,
+}
+
+impl GetPaneRenderChanges {
+    /// Builds a request with the historical behavior: no seqno
+    /// hint and bonus lines included.
+    pub fn new(pane_id: PaneId) -> Self {
+        Self {
+            pane_id,
+            since_seqno: None,
+            want_bonus_lines: true,
+        }
+    }
+
+    /// Produces the `bonus_lines` for a response to this request,
+    /// sending nothing when the client opted out.
+    pub fn bonus_lines(&self, lines: Vec<(StableRowIndex, Line)>) -> SerializedLines {
+        if self.want_bonus_lines {
+            lines.into()
+        } else {
+            SerializedLines::default()
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -963,6 +2623,21 @@ pub struct GetPaneRenderableDimensionsResponse {
     pub dimensions: RenderableDimensions,
 }
 
+/// Asks for just the cursor position of `pane_id`; a cheap poll for
+/// clients that only need to keep the cursor in the right spot, rather
+/// than fetching a full `GetPaneRenderChangesResponse`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCursorPosition {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCursorPositionResponse {
+    pub pane_id: PaneId,
+    pub cursor_position: StableCursorPosition,
+    pub seqno: SequenceNo,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct LivenessResponse {
     pub pane_id: PaneId,
@@ -990,6 +2665,11 @@ pub struct GetPaneRenderChangesResponse {
 pub struct GetLines {
     pub pane_id: PaneId,
     pub lines: Vec<Range<StableRowIndex>>,
+    /// When set, only the cells in this column range are returned
+    /// for each line.  `None` returns whole lines, as it does for a
+    /// client that predates this field.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub cols: Option<Range<usize>>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -1065,10 +2745,32 @@ impl SerializedLines {
         };
         (lines, self.images)
     }
-}
 
-impl From<Vec<(StableRowIndex, Line)>> for SerializedLines {
-    fn from(mut lines: Vec<(StableRowIndex, Line)>) -> Self {
+    /// Serialize lines, first trimming each of them to `cols` when it
+    /// is `Some`, as requested by `GetLines::cols`.
+    /// Hyperlink coordinates index into the trimmed lines so that
+    /// `extract_data` restores them onto the right cells, while the
+    /// `cell_idx` of any image remains an absolute column in the pane.
+    pub fn with_column_range(
+        mut lines: Vec<(StableRowIndex, Line)>,
+        cols: Option<Range<usize>>,
+    ) -> Self {
+        let col_offset = match cols {
+            Some(cols) => {
+                for (_, line) in lines.iter_mut() {
+                    let seqno = line.current_seqno();
+                    let mut trimmed = line.split_off(cols.start, seqno);
+                    trimmed.split_off(cols.len(), seqno);
+                    *line = trimmed;
+                }
+                cols.start
+            }
+            None => 0,
+        };
+        Self::decompose(lines, col_offset)
+    }
+
+    fn decompose(mut lines: Vec<(StableRowIndex, Line)>, col_offset: usize) -> Self {
         let mut hyperlinks = vec![];
         let mut images = vec![];
 
@@ -1126,7 +2828,7 @@ impl From<Vec<(StableRowIndex, Line)>> for SerializedLines {
                             imcell.padding();
                         images.push(SerializedImageCell {
                             line_idx: *stable_row_idx,
-                            cell_idx: x,
+                            cell_idx: col_offset + x,
                             top_left: imcell.top_left(),
                             bottom_right: imcell.bottom_right(),
                             z_index: imcell.z_index(),
@@ -1162,16 +2864,145 @@ impl From<Vec<(StableRowIndex, Line)>> for SerializedLines {
     }
 }
 
+impl From<Vec<(StableRowIndex, Line)>> for SerializedLines {
+    fn from(lines: Vec<(StableRowIndex, Line)>) -> Self {
+        Self::with_column_range(lines, None)
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetLinesResponse {
     pub pane_id: PaneId,
     pub lines: SerializedLines,
 }
 
+/// Requests the text of the rows in `range` as logical lines, for
+/// clients implementing copy with reflow.
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
-pub struct EraseScrollbackRequest {
+pub struct GetLogicalLines {
+    pub pane_id: PaneId,
+    pub range: Range<StableRowIndex>,
+}
+
+/// Unlike `GetLinesResponse`, each entry is a logical line: physical
+/// rows joined by soft wraps have been combined into a single string.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetLogicalLinesResponse {
+    pub pane_id: PaneId,
+    pub lines: Vec<String>,
+}
+
+/// Like `GetLines`, but the server replies with a sequence of
+/// `GetLinesChunkResponse`s of at most `chunk_rows` lines each,
+/// so that a client requesting a lot of scrollback can render it
+/// as it arrives rather than waiting for all of it.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetLinesChunked {
+    pub pane_id: PaneId,
+    pub lines: Vec<Range<StableRowIndex>>,
+    pub chunk_rows: usize,
+}
+
+/// One chunk of the reply to `GetLinesChunked`.  All of the chunks
+/// carry the serial of the request; `is_last` is set only on the
+/// final one.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetLinesChunkResponse {
+    pub pane_id: PaneId,
+    pub lines: SerializedLines,
+    pub is_last: bool,
+}
+
+impl GetLinesChunkResponse {
+    /// Splits `lines` into responses of at most `chunk_rows` lines.
+    /// There is always at least one response, so that the client
+    /// sees `is_last` even when there are no lines to send.
+    pub fn split(
+        pane_id: PaneId,
+        lines: Vec<(StableRowIndex, Line)>,
+        chunk_rows: usize,
+    ) -> Vec<Self> {
+        let chunk_rows = chunk_rows.max(1);
+        let mut chunks: Vec<Self> = Vec::new();
+        let mut lines = lines.into_iter().peekable();
+        loop {
+            let chunk: Vec<_> = lines.by_ref().take(chunk_rows).collect();
+            let is_last = lines.peek().is_none();
+            chunks.push(Self {
+                pane_id,
+                lines: chunk.into(),
+                is_last,
+            });
+            if is_last {
+                return chunks;
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetScrollbackLineCount {
+    pub pane_id: PaneId,
+}
+
+/// The valid `StableRowIndex` range of a pane is
+/// `first_row..first_row + count`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetScrollbackLineCountResponse {
+    pub pane_id: PaneId,
+    pub first_row: StableRowIndex,
+    pub count: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneFontInfo {
+    pub pane_id: PaneId,
+}
+
+/// The pixel dimensions of a single cell in the pane, derived from
+/// its `TerminalSize`, which clients need to place image textures.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneFontInfoResponse {
+    pub pane_id: PaneId,
+    pub cell_width: u16,
+    pub cell_height: u16,
+    pub dpi: u32,
+}
+
+/// Requests the text currently selected in a pane, for clients
+/// implementing copy-on-select or a selection shared between clients.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSelectionText {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSelectionTextResponse {
+    pub pane_id: PaneId,
+    /// `None` if there is no selection in the pane
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct EraseScrollbackRequest {
     pub pane_id: PaneId,
     pub erase_mode: ScrollbackEraseMode,
+    /// When set, only the rows in this range are erased and
+    /// `erase_mode` is ignored; eg: to trim everything before
+    /// a bookmark.  A client that predates this field always
+    /// erases according to `erase_mode`.
+    #[serde(default, deserialize_with = "trailing_default")]
+    pub range: Option<Range<StableRowIndex>>,
+}
+
+/// Asks the server to abandon the outstanding request that was sent
+/// with `serial`, such as a slow `SearchScrollbackRequest` whose
+/// result the client no longer needs.  The server aborts it and
+/// replies to the original serial with an `ErrorResponse`; if that
+/// request has already completed, this has no effect.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct CancelRequest {
+    pub serial: u64,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -1240,6 +3071,7 @@ mod test {
         assert_eq!(
             DecodedPdu {
                 serial: 0x40,
+                frame_seq: None,
                 pdu: Pdu::Ping(Ping {})
             },
             Pdu::decode(encoded.as_slice()).unwrap()
@@ -1288,6 +3120,7 @@ mod test {
             Pdu::try_read_and_decode(&mut cursor, &mut read_buffer).unwrap(),
             Some(DecodedPdu {
                 serial: 1,
+                frame_seq: None,
                 pdu: Pdu::Ping(Ping {})
             })
         );
@@ -1295,6 +3128,7 @@ mod test {
             Pdu::try_read_and_decode(&mut cursor, &mut read_buffer).unwrap(),
             Some(DecodedPdu {
                 serial: 2,
+                frame_seq: None,
                 pdu: Pdu::Pong(Pong {})
             })
         );
@@ -1305,6 +3139,122 @@ mod test {
         );
     }
 
+    #[test]
+    fn decode_from_slice_complete_frame() {
+        let mut encoded = Vec::new();
+        let pdu = Pdu::SendPaste(SendPaste {
+            pane_id: 2,
+            data: "hello".repeat(100),
+        });
+        pdu.encode(&mut encoded, 7).unwrap();
+
+        let (decoded, consumed) = Pdu::decode_from_slice(&encoded).unwrap().unwrap();
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(decoded.serial, 7);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn decode_from_slice_partial_frame() {
+        let mut encoded = Vec::new();
+        Pdu::ListTabs(ListTabs { window_id: None })
+            .encode(&mut encoded, 1)
+            .unwrap();
+        for len in 0..encoded.len() {
+            assert!(Pdu::decode_from_slice(&encoded[..len]).unwrap().is_none());
+        }
+        assert!(Pdu::decode_from_slice(&encoded).unwrap().is_some());
+    }
+
+    #[test]
+    fn decode_from_slice_back_to_back_frames() {
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
+        let first_len = encoded.len();
+        Pdu::GetSelectionText(GetSelectionText { pane_id: 4 })
+            .encode(&mut encoded, 2)
+            .unwrap();
+
+        let (first, consumed) = Pdu::decode_from_slice(&encoded).unwrap().unwrap();
+        assert_eq!(consumed, first_len);
+        assert_eq!(first.serial, 1);
+        assert_eq!(first.pdu, Pdu::Ping(Ping {}));
+
+        let (second, consumed) = Pdu::decode_from_slice(&encoded[first_len..])
+            .unwrap()
+            .unwrap();
+        assert_eq!(consumed, encoded.len() - first_len);
+        assert_eq!(second.serial, 2);
+        assert_eq!(
+            second.pdu,
+            Pdu::GetSelectionText(GetSelectionText { pane_id: 4 })
+        );
+    }
+
+    #[test]
+    fn pdu_stream_decoder_yields_each_pdu() {
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
+        Pdu::ListTabs(ListTabs { window_id: Some(3) })
+            .encode(&mut encoded, 2)
+            .unwrap();
+        Pdu::SendPaste(SendPaste {
+            pane_id: 5,
+            data: "x".repeat(10_000),
+        })
+        .encode(&mut encoded, 3)
+        .unwrap();
+        Pdu::Pong(Pong {}).encode(&mut encoded, 4).unwrap();
+
+        let mut decoder = PduStreamDecoder::new(Cursor::new(encoded.as_slice()));
+        let first = decoder.next_pdu().unwrap().unwrap();
+        assert_eq!(first.serial, 1);
+        assert_eq!(first.pdu, Pdu::Ping(Ping {}));
+
+        let rest: Vec<DecodedPdu> = decoder.by_ref().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            rest.iter().map(|d| d.serial).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(rest[0].pdu, Pdu::ListTabs(ListTabs { window_id: Some(3) }));
+        assert_eq!(rest[1].pdu.pdu_name(), "SendPaste");
+        assert_eq!(rest[2].pdu, Pdu::Pong(Pong {}));
+        assert_eq!(decoder.buffered_len(), 0);
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn pdu_stream_decoder_eof_and_would_block() {
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
+        Pdu::Pong(Pong {}).encode(&mut encoded, 2).unwrap();
+        encoded.pop();
+
+        let mut decoder = PduStreamDecoder::new(Cursor::new(encoded.as_slice()));
+        assert_eq!(decoder.next_pdu().unwrap().unwrap().serial, 1);
+        let err = decoder.next_pdu().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<std::io::Error>().unwrap().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+
+        // A truncated frame at EOF is reported through the iterator too
+        let mut decoder = PduStreamDecoder::new(Cursor::new(encoded.as_slice()));
+        assert!(decoder.next().unwrap().is_ok());
+        assert!(decoder.next().unwrap().is_err());
+        assert!(decoder.next().is_none());
+
+        struct WouldBlock;
+        impl std::io::Read for WouldBlock {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::WouldBlock.into())
+            }
+        }
+        let mut decoder = PduStreamDecoder::new(WouldBlock);
+        assert!(decoder.next_pdu().unwrap().is_none());
+        assert!(decoder.next().is_none());
+    }
+
     #[test]
     fn test_pdu_ping_base91() {
         let mut encoded = Vec::new();
@@ -1317,6 +3267,7 @@ mod test {
         assert_eq!(
             DecodedPdu {
                 serial: 0x41,
+                frame_seq: None,
                 pdu: Pdu::Ping(Ping {})
             },
             Pdu::decode(decoded.as_slice()).unwrap()
@@ -1331,6 +3282,7 @@ mod test {
         assert_eq!(
             DecodedPdu {
                 serial: 0x42,
+                frame_seq: None,
                 pdu: Pdu::Pong(Pong {})
             },
             Pdu::decode(encoded.as_slice()).unwrap()
@@ -1344,12 +3296,223 @@ mod test {
         assert_eq!(
             DecodedPdu {
                 serial: 0x42,
+                frame_seq: None,
                 pdu: Pdu::Invalid { ident: 0xdeadbeef }
             },
             Pdu::decode(encoded.as_slice()).unwrap()
         );
     }
 
+    /// A metrics recorder that remembers the `ident` label of each
+    /// increment of the `pdu.decode.unknown_ident` counter
+    #[derive(Default)]
+    struct UnknownIdentRecorder {
+        increments: Arc<std::sync::Mutex<Vec<(String, u64)>>>,
+    }
+
+    struct UnknownIdentCounter {
+        ident: String,
+        increments: Arc<std::sync::Mutex<Vec<(String, u64)>>>,
+    }
+
+    impl metrics::CounterFn for UnknownIdentCounter {
+        fn increment(&self, value: u64) {
+            self.increments
+                .lock()
+                .unwrap()
+                .push((self.ident.clone(), value));
+        }
+
+        fn absolute(&self, _value: u64) {}
+    }
+
+    impl metrics::Recorder for UnknownIdentRecorder {
+        fn describe_counter(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_gauge(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn describe_histogram(
+            &self,
+            _key: metrics::KeyName,
+            _unit: Option<metrics::Unit>,
+            _description: metrics::SharedString,
+        ) {
+        }
+
+        fn register_counter(
+            &self,
+            key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Counter {
+            if key.name() != "pdu.decode.unknown_ident" {
+                return metrics::Counter::noop();
+            }
+            let ident = key
+                .labels()
+                .find(|label| label.key() == "ident")
+                .map(|label| label.value().to_string())
+                .unwrap_or_default();
+            metrics::Counter::from_arc(Arc::new(UnknownIdentCounter {
+                ident,
+                increments: Arc::clone(&self.increments),
+            }))
+        }
+
+        fn register_gauge(
+            &self,
+            _key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Gauge {
+            metrics::Gauge::noop()
+        }
+
+        fn register_histogram(
+            &self,
+            _key: &metrics::Key,
+            _metadata: &metrics::Metadata<'_>,
+        ) -> metrics::Histogram {
+            metrics::Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn bogus_pdu_records_unknown_ident_counter() {
+        let recorder = UnknownIdentRecorder::default();
+        let mut encoded = Vec::new();
+        encode_raw(0xdeadbeef, 0x42, b"hello", false, &mut encoded).unwrap();
+        let decoded =
+            metrics::with_local_recorder(&recorder, || Pdu::decode(encoded.as_slice()).unwrap());
+        assert_eq!(decoded.pdu, Pdu::Invalid { ident: 0xdeadbeef });
+        assert_eq!(
+            *recorder.increments.lock().unwrap(),
+            vec![("3735928559".to_string(), 1)]
+        );
+
+        // Known idents don't touch the counter
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 0x43).unwrap();
+        metrics::with_local_recorder(&recorder, || Pdu::decode(encoded.as_slice()).unwrap());
+        assert_eq!(recorder.increments.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn bogus_pdu_debug_has_note() {
+        let dbg = format!("{:?}", Pdu::Invalid { ident: 0xdeadbeef });
+        assert!(dbg.contains("ident: 3735928559"), "{}", dbg);
+        assert!(dbg.contains("peer may be newer"), "{}", dbg);
+        assert_eq!(format!("{:?}", Pdu::Ping(Ping {})), "Ping(Ping)");
+    }
+
+    #[test]
+    fn truncated_known_pdu_error_names_type() {
+        let mut encoded = Vec::new();
+        encode_raw(9, 0x42, b"", false, &mut encoded).unwrap();
+        let err = Pdu::decode(encoded.as_slice()).expect_err("empty payload cannot decode");
+        let message = err.to_string();
+        assert!(message.contains("WriteToPane"), "{}", message);
+        assert!(message.contains("ident 9"), "{}", message);
+    }
+
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+    #[test]
+    fn truncated_known_pdu_error_names_type_async() {
+        let mut encoded = Vec::new();
+        encode_raw(9, 0x42, b"", false, &mut encoded).unwrap();
+        let err = smol::block_on(Pdu::decode_async(&mut smol::io::Cursor::new(encoded), None))
+            .expect_err("empty payload cannot decode");
+        assert!(err.to_string().contains("WriteToPane"), "{}", err);
+    }
+
+    // --- peek_frame_header tests ---
+
+    #[test]
+    fn peek_frame_header_small_frame() {
+        let mut encoded = Vec::new();
+        encode_raw(0x2, 0x40, b"hi", false, &mut encoded).unwrap();
+        let header = peek_frame_header(&encoded).unwrap().unwrap();
+        assert_eq!(
+            header,
+            FrameHeader {
+                tagged_len: 4,
+                serial: 0x40,
+                ident: 0x2,
+                frame_seq: None,
+                is_compressed: false,
+                header_len: 3,
+            }
+        );
+        assert_eq!(header.data_len(), 2);
+        assert_eq!(
+            &encoded[header.header_len..header.header_len + header.data_len()],
+            b"hi"
+        );
+    }
+
+    #[test]
+    fn peek_frame_header_multibyte_fields() {
+        let data = vec![7u8; 300];
+        let mut encoded = Vec::new();
+        encode_raw(0x1234, 0x56789, &data, true, &mut encoded).unwrap();
+        let header = peek_frame_header(&encoded).unwrap().unwrap();
+        assert_eq!(header.serial, 0x56789);
+        assert_eq!(header.ident, 0x1234);
+        assert!(header.is_compressed);
+        assert_ne!(header.tagged_len & COMPRESSED_MASK, 0);
+        assert_eq!(
+            header.header_len,
+            encoded_length(header.tagged_len)
+                + encoded_length(header.serial)
+                + encoded_length(header.ident)
+        );
+        assert_eq!(header.data_len(), data.len());
+        assert_eq!(header.header_len + header.data_len(), encoded.len());
+        assert_eq!(&encoded[header.header_len..], data.as_slice());
+    }
+
+    #[test]
+    fn peek_frame_header_without_data() {
+        let mut encoded = Vec::new();
+        encode_raw(0x1234, 0x56789, &[1u8; 300], false, &mut encoded).unwrap();
+        let header = peek_frame_header(&encoded).unwrap().unwrap();
+        let header_only = peek_frame_header(&encoded[..header.header_len])
+            .unwrap()
+            .unwrap();
+        assert_eq!(header, header_only);
+    }
+
+    #[test]
+    fn peek_frame_header_truncated_prefix() {
+        let mut encoded = Vec::new();
+        encode_raw(0x1234, 0x56789, b"data", false, &mut encoded).unwrap();
+        let header = peek_frame_header(&encoded).unwrap().unwrap();
+        for end in 0..header.header_len {
+            assert_eq!(peek_frame_header(&encoded[..end]).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn peek_frame_header_rejects_impossible_length() {
+        // len=1, serial=1, ident=1 => encoded(serial)+encoded(ident)=2
+        let err = peek_frame_header(&[1u8, 1u8, 1u8]).expect_err("malformed frame");
+        assert!(
+            err.to_string().contains("sizes don't make sense"),
+            "{}",
+            err
+        );
+    }
+
     // --- encoded_length tests ---
 
     #[test]
@@ -1377,6 +3540,73 @@ mod test {
         assert_eq!(encoded_length(u64::MAX), 10);
     }
 
+    // --- frame sequence number tests ---
+
+    #[test]
+    fn sequenced_frame_roundtrip() {
+        for frame_seq in [0, 1, 0x7f, 0x80, u64::MAX] {
+            let mut encoded = Vec::new();
+            encode_sequenced_raw(0x42, 7, frame_seq, b"hello", false, &mut encoded).unwrap();
+            let decoded = decode_raw(encoded.as_slice()).unwrap();
+            assert_eq!(decoded.frame_seq, Some(frame_seq));
+            assert_eq!(decoded.serial, 7);
+            assert_eq!(decoded.ident, 0x42);
+            assert_eq!(decoded.data, b"hello");
+            assert!(!decoded.is_compressed);
+
+            let header = peek_frame_header(&encoded).unwrap().unwrap();
+            assert_eq!(header.frame_seq, Some(frame_seq));
+            assert_eq!(header.data_len(), 5);
+            assert_eq!(&encoded[header.header_len..], b"hello");
+        }
+    }
+
+    #[test]
+    fn sequenced_compressed_frame_roundtrip() {
+        let mut encoded = Vec::new();
+        encode_sequenced_raw(5, 10, 3, b"payload", true, &mut encoded).unwrap();
+        let decoded = decode_raw(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.frame_seq, Some(3));
+        assert!(decoded.is_compressed);
+        assert_eq!(decoded.data, b"payload");
+    }
+
+    #[test]
+    fn unsequenced_frame_has_no_frame_seq() {
+        let mut encoded = Vec::new();
+        encode_raw(0x42, 7, b"hello", false, &mut encoded).unwrap();
+        assert_eq!(decode_raw(encoded.as_slice()).unwrap().frame_seq, None);
+        assert_eq!(
+            peek_frame_header(&encoded).unwrap().unwrap().frame_seq,
+            None
+        );
+
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
+        assert_eq!(Pdu::decode(encoded.as_slice()).unwrap().frame_seq, None);
+    }
+
+    #[test]
+    fn sequenced_pdu_gap_is_observable() {
+        let mut stream = Vec::new();
+        for (serial, frame_seq) in [(1, 1), (2, 2), (3, 4)] {
+            Pdu::Ping(Ping {})
+                .encode_sequenced(&mut stream, serial, frame_seq)
+                .unwrap();
+        }
+
+        let mut cursor = Cursor::new(stream.as_slice());
+        let mut seqs = vec![];
+        while (cursor.position() as usize) < stream.len() {
+            let decoded = Pdu::decode(&mut cursor).unwrap();
+            assert_eq!(decoded.pdu, Pdu::Ping(Ping {}));
+            seqs.push(decoded.frame_seq.unwrap());
+        }
+        assert_eq!(seqs, vec![1, 2, 4]);
+        let gaps: Vec<_> = seqs.windows(2).filter(|w| w[1] != w[0] + 1).collect();
+        assert_eq!(gaps, vec![&[2, 4]]);
+    }
+
     // --- encode_raw / decode_raw roundtrip tests ---
 
     #[test]
@@ -1426,6 +3656,46 @@ mod test {
         assert_eq!(vec_result, write_result);
     }
 
+    // --- encode_raw_small fast path tests ---
+
+    #[test]
+    fn encode_raw_small_matches_general_path_for_ping() {
+        let (buffer, size) = encode_raw_small(1, 0x40, b"", false).unwrap();
+        assert_eq!(&buffer[..size], &[2, 0x40, 1]);
+        assert_eq!(
+            &buffer[..size],
+            encode_raw_as_vec(1, 0x40, b"", false).unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn encode_raw_small_matches_general_path_up_to_limit() {
+        let payload = [b'z'; 125];
+        for serial in [0u64, 1, 0x40, 0x7f] {
+            for data_len in [0usize, 1, 64, 124, 125] {
+                let data = &payload[..data_len];
+                let (buffer, size) = encode_raw_small(2, serial, data, false).unwrap();
+                assert_eq!(
+                    &buffer[..size],
+                    encode_raw_as_vec(2, serial, data, false)
+                        .unwrap()
+                        .as_slice(),
+                    "serial={} data_len={}",
+                    serial,
+                    data_len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn encode_raw_small_declines_frames_needing_varints() {
+        assert!(encode_raw_small(0x80, 1, b"", false).is_none());
+        assert!(encode_raw_small(1, 0x80, b"", false).is_none());
+        assert!(encode_raw_small(1, 1, &[0u8; 126], false).is_none());
+        assert!(encode_raw_small(1, 1, b"", true).is_none());
+    }
+
     // --- COMPRESSED_MASK tests ---
 
     #[test]
@@ -1473,7 +3743,8 @@ mod test {
     fn serialize_never_mode() {
         // Even large data stays uncompressed with Never mode
         let val: Vec<u8> = vec![0xAA; 512];
-        let (data, is_compressed) = serialize_with_mode(&val, CompressionMode::Never).unwrap();
+        let (data, is_compressed) =
+            serialize_with_mode(&val, CompressionMode::Never, "test").unwrap();
         assert!(!is_compressed);
         let result: Vec<u8> = deserialize(data.as_slice(), false).unwrap();
         assert_eq!(result, val);
@@ -1482,7 +3753,8 @@ mod test {
     #[test]
     fn serialize_always_mode() {
         let val: Vec<u8> = vec![0xBB; 512];
-        let (data, is_compressed) = serialize_with_mode(&val, CompressionMode::Always).unwrap();
+        let (data, is_compressed) =
+            serialize_with_mode(&val, CompressionMode::Always, "test").unwrap();
         assert!(is_compressed);
         let result: Vec<u8> = deserialize(data.as_slice(), true).unwrap();
         assert_eq!(result, val);
@@ -1492,44 +3764,286 @@ mod test {
     fn serialize_auto_mode_large_data() {
         // Repetitive large data should compress well
         let val: Vec<u8> = vec![0xCC; 4096];
-        let (data, is_compressed) = serialize_with_mode(&val, CompressionMode::Auto).unwrap();
+        let (data, is_compressed) =
+            serialize_with_mode(&val, CompressionMode::Auto, "test").unwrap();
         // Auto may or may not compress depending on ratio, but roundtrip must work
         let result: Vec<u8> = deserialize(data.as_slice(), is_compressed).unwrap();
         assert_eq!(result, val);
     }
 
-    // --- InputSerial tests ---
-
     #[test]
-    fn input_serial_empty() {
-        let empty = InputSerial::empty();
-        assert_eq!(format!("{:?}", empty), "InputSerial(0)");
+    fn compression_ratio_of_compressible_payload() {
+        let val: Vec<u8> = vec![0xDD; 4096];
+        let (uncompressed, _) = serialize_with_mode(&val, CompressionMode::Never, "test").unwrap();
+        let (compressed, is_compressed) =
+            serialize_with_mode(&val, CompressionMode::Always, "test").unwrap();
+        assert!(is_compressed);
+        let ratio = compression_ratio(compressed.len(), uncompressed.len());
+        assert!(ratio < 0.1, "ratio {} should be well below 1.0", ratio);
     }
 
     #[test]
-    fn input_serial_now_nonzero() {
-        let now = InputSerial::now();
-        // Should be a large number of milliseconds since epoch
-        assert_ne!(format!("{:?}", now), "InputSerial(0)");
+    fn compression_ratio_edge_cases() {
+        assert_eq!(compression_ratio(50, 100), 0.5);
+        assert_eq!(compression_ratio(120, 100), 1.2);
+        assert_eq!(compression_ratio(0, 0), 1.0);
     }
 
-    #[test]
-    fn input_serial_elapsed_millis() {
-        let before = InputSerial::now();
-        std::thread::sleep(std::time::Duration::from_millis(10));
-        let elapsed = before.elapsed_millis();
-        assert!(
-            elapsed >= 5,
-            "{}",
-            format!("elapsed should be at least ~10ms, got {}", elapsed)
-        );
+    // --- forward compatibility tests ---
+
+    /// Asserts that a payload encoded from `old`, a struct as it was
+    /// before fields were appended to it, decodes as `expected`, and
+    /// that `expected` can still be decoded by a peer that only knows
+    /// the old layout.
+    fn assert_forward_compatible<Old, New>(old: &Old, expected: &New)
+    where
+        Old: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+        New: Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug,
+    {
+        for mode in [CompressionMode::Never, CompressionMode::Always] {
+            let (data, is_compressed) = serialize_with_mode(old, mode, "test").unwrap();
+            let decoded: New = deserialize(data.as_slice(), is_compressed).unwrap();
+            assert_eq!(&decoded, expected, "{:?}", mode);
+
+            let (data, is_compressed) = serialize_with_mode(expected, mode, "test").unwrap();
+            let decoded: Old = deserialize(data.as_slice(), is_compressed).unwrap();
+            assert_eq!(&decoded, old, "{:?}", mode);
+        }
     }
 
-    #[test]
-    fn input_serial_from_system_time() {
-        let time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(12345);
-        let serial: InputSerial = time.into();
-        assert_eq!(format!("{:?}", serial), "InputSerial(12345)");
+    /// `SpawnV2` as it was before `env` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct SpawnV2WithoutEnv {
+        domain: config::keyassignment::SpawnTabDomain,
+        window_id: Option<WindowId>,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        size: TerminalSize,
+        workspace: String,
+    }
+
+    #[test]
+    fn spawn_v2_env_is_forward_compatible() {
+        let old = SpawnV2WithoutEnv {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: Some(3),
+            command: Some(CommandBuilder::new("bash")),
+            command_dir: Some("/tmp".to_string()),
+            size: TerminalSize::default(),
+            workspace: "default".to_string(),
+        };
+        let new = SpawnV2 {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: Some(3),
+            command: Some(CommandBuilder::new("bash")),
+            command_dir: Some("/tmp".to_string()),
+            size: TerminalSize::default(),
+            workspace: "default".to_string(),
+            env: HashMap::new(),
+        };
+        assert_forward_compatible(&old, &new);
+    }
+
+    #[test]
+    fn trailing_default_does_not_mask_truncation() {
+        let old = SpawnV2WithoutEnv {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: None,
+            command: None,
+            command_dir: None,
+            size: TerminalSize::default(),
+            workspace: "default".to_string(),
+        };
+        let (data, _) = serialize_with_mode(&old, CompressionMode::Never, "test").unwrap();
+        let truncated = &data[..data.len() - 1];
+        assert!(deserialize::<SpawnV2, _>(truncated, false).is_err());
+
+        // Truncating part way through `env` is an error too, rather
+        // than being padded out into bogus entries
+        let mut env = HashMap::new();
+        env.insert("a".to_string(), "1".to_string());
+        env.insert("b".to_string(), "2".to_string());
+        let new = SpawnV2 {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: None,
+            command: None,
+            command_dir: None,
+            size: TerminalSize::default(),
+            workspace: "default".to_string(),
+            env,
+        };
+        let (data, _) = serialize_with_mode(&new, CompressionMode::Never, "test").unwrap();
+        let (old_data, _) = serialize_with_mode(&old, CompressionMode::Never, "test").unwrap();
+        // Each entry is a one byte length and one byte of text for
+        // both key and value, so this covers dropping the tail of the
+        // second entry, all of it, and the first entry too
+        for cut in 1..=(data.len() - old_data.len() - 1) {
+            let truncated = &data[..data.len() - cut];
+            assert!(
+                deserialize::<SpawnV2, _>(truncated, false).is_err(),
+                "cut {}",
+                cut
+            );
+        }
+        let spawn: SpawnV2 = deserialize(data.as_slice(), false).unwrap();
+        assert_eq!(spawn, new);
+    }
+
+    /// `GetCodecVersionResponse` as it was before `server_epoch` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct GetCodecVersionResponseWithoutEpoch {
+        codec_vers: usize,
+        version_string: String,
+        executable_path: PathBuf,
+        config_file_path: Option<PathBuf>,
+    }
+
+    #[test]
+    fn codec_version_response_decodes_from_older_server() {
+        let old = GetCodecVersionResponseWithoutEpoch {
+            codec_vers: 4,
+            version_string: "old".to_string(),
+            executable_path: PathBuf::from("/bin/old"),
+            config_file_path: Some(PathBuf::from("/etc/old.lua")),
+        };
+        let new = GetCodecVersionResponse {
+            codec_vers: 4,
+            version_string: "old".to_string(),
+            executable_path: PathBuf::from("/bin/old"),
+            config_file_path: Some(PathBuf::from("/etc/old.lua")),
+            server_epoch: 0,
+        };
+        assert_forward_compatible(&old, &new);
+
+        // The handshake must still see the version so that the client
+        // can report the mismatch rather than a decode error.
+        let (data, is_compressed) = serialize(&old).unwrap();
+        let mut buf = Vec::new();
+        encode_raw(27, 5, &data, is_compressed, &mut buf).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 5);
+        assert_eq!(decoded.pdu, Pdu::GetCodecVersionResponse(new));
+    }
+
+    // --- decompression limit tests ---
+
+    #[test]
+    fn deserialize_rejects_payload_inflating_past_limit() {
+        // 1MiB of zeros compresses to a handful of bytes
+        let val: Vec<u8> = vec![0; 1024 * 1024];
+        let (data, is_compressed) = serialize(&val).unwrap();
+        assert!(is_compressed);
+        assert!(data.len() < 4096);
+
+        let err =
+            deserialize_with_limit::<Vec<u8>, _>(data.as_slice(), true, 64 * 1024).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("exceeds the limit of 65536 bytes"),
+            "{:#}",
+            err
+        );
+
+        let result: Vec<u8> = deserialize(data.as_slice(), true).unwrap();
+        assert_eq!(result, val);
+    }
+
+    #[test]
+    fn deserialize_accepts_payload_exactly_at_limit() {
+        let val: Vec<u8> = vec![0x11; 4096];
+        let (data, _) = serialize_with_mode(&val, CompressionMode::Always, "test").unwrap();
+        let (raw, _) = serialize_with_mode(&val, CompressionMode::Never, "test").unwrap();
+        let result: Vec<u8> =
+            deserialize_with_limit(data.as_slice(), true, raw.len() as u64).unwrap();
+        assert_eq!(result, val);
+        assert!(
+            deserialize_with_limit::<Vec<u8>, _>(data.as_slice(), true, raw.len() as u64 - 1)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn pdu_decode_with_limit_rejects_decompression_bomb() {
+        let pdu = Pdu::WriteToPane(WriteToPane {
+            pane_id: 1,
+            data: vec![0; 1024 * 1024],
+        });
+        let mut buf = Vec::new();
+        pdu.encode(&mut buf, 1).unwrap();
+        assert!(buf.len() < 4096);
+        assert!(Pdu::decode_with_limit(buf.as_slice(), 64 * 1024).is_err());
+        assert_eq!(Pdu::decode(buf.as_slice()).unwrap().pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_decode_with_limit_applies_to_batched_pdus() {
+        let write = Pdu::WriteToPane(WriteToPane {
+            pane_id: 1,
+            data: vec![0; 1024 * 1024],
+        });
+        let batch = Pdu::Batch(Batch {
+            requests: vec![write],
+        });
+        let mut buf = Vec::new();
+        batch.encode(&mut buf, 1).unwrap();
+        assert!(buf.len() < 4096);
+
+        let err = Pdu::decode_with_limit(buf.as_slice(), 64 * 1024).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("exceeds the limit"),
+            "{:#}",
+            err
+        );
+        // The failure doesn't leave the limit behind for later decodes
+        assert_eq!(Pdu::decode(buf.as_slice()).unwrap().pdu, batch);
+    }
+
+    #[test]
+    fn decode_frames_with_limit_applies_to_envelope() {
+        let write = Pdu::WriteToPane(WriteToPane {
+            pane_id: 1,
+            data: vec![0; 1024 * 1024],
+        });
+        let mut envelope = Vec::new();
+        Pdu::encode_stream_compressed([(1, &write)], &mut envelope).unwrap();
+        assert!(Pdu::decode_frames_with_limit(envelope.as_slice(), 64 * 1024).is_err());
+        assert_eq!(
+            Pdu::decode_frames(envelope.as_slice()).unwrap()[0].pdu,
+            write
+        );
+    }
+
+    // --- InputSerial tests ---
+
+    #[test]
+    fn input_serial_empty() {
+        let empty = InputSerial::empty();
+        assert_eq!(format!("{:?}", empty), "InputSerial(0)");
+    }
+
+    #[test]
+    fn input_serial_now_nonzero() {
+        let now = InputSerial::now();
+        // Should be a large number of milliseconds since epoch
+        assert_ne!(format!("{:?}", now), "InputSerial(0)");
+    }
+
+    #[test]
+    fn input_serial_elapsed_millis() {
+        let before = InputSerial::now();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        let elapsed = before.elapsed_millis();
+        assert!(
+            elapsed >= 5,
+            "{}",
+            format!("elapsed should be at least ~10ms, got {}", elapsed)
+        );
+    }
+
+    #[test]
+    fn input_serial_from_system_time() {
+        let time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_millis(12345);
+        let serial: InputSerial = time.into();
+        assert_eq!(format!("{:?}", serial), "InputSerial(12345)");
     }
 
     #[test]
@@ -1561,6 +4075,11 @@ mod test {
             size: TerminalSize::default(),
         })
         .is_user_input());
+        assert!(Pdu::ResizeTab(ResizeTab {
+            tab_id: 0,
+            size: TerminalSize::default(),
+        })
+        .is_user_input());
     }
 
     #[test]
@@ -1573,6 +4092,48 @@ mod test {
         assert!(!Pdu::Invalid { ident: 99 }.is_user_input());
     }
 
+    // --- Pdu::is_response / is_notification tests ---
+
+    #[test]
+    fn pdu_classify_response() {
+        for pdu in [
+            Pdu::Pong(Pong {}),
+            Pdu::UnitResponse(UnitResponse {}),
+            Pdu::ErrorResponse(ErrorResponse {
+                reason: "nope".to_string(),
+            }),
+            Pdu::ListTabsResponse(ListTabsResponse { tabs: vec![] }),
+        ] {
+            assert!(pdu.is_response(), "{:?}", pdu);
+            assert!(!pdu.is_notification(), "{:?}", pdu);
+        }
+    }
+
+    #[test]
+    fn pdu_classify_request() {
+        for pdu in [
+            Pdu::Ping(Ping {}),
+            Pdu::ListPanes(ListPanes {}),
+            Pdu::GetPaneFontInfo(GetPaneFontInfo { pane_id: 1 }),
+            Pdu::Invalid { ident: 1000 },
+        ] {
+            assert!(!pdu.is_response(), "{:?}", pdu);
+            assert!(!pdu.is_notification(), "{:?}", pdu);
+        }
+    }
+
+    #[test]
+    fn pdu_classify_notification() {
+        for pdu in [
+            Pdu::PaneFocused(PaneFocused { pane_id: 1 }),
+            Pdu::PaneRemoved(PaneRemoved { pane_id: 1 }),
+        ] {
+            assert!(pdu.is_notification(), "{:?}", pdu);
+            assert!(!pdu.is_response(), "{:?}", pdu);
+            assert!(EventKind::of(&pdu).is_some());
+        }
+    }
+
     // --- Pdu::pdu_name tests ---
 
     #[test]
@@ -1599,6 +4160,24 @@ mod test {
         assert_eq!(Pdu::Invalid { ident: 0 }.pdu_name(), "Invalid");
     }
 
+    // --- Pdu::ident tests ---
+
+    #[test]
+    fn pdu_ident_matches_wire_numbers() {
+        assert_eq!(Pdu::Ping(Ping {}).ident(), 1);
+        assert_eq!(Pdu::Pong(Pong {}).ident(), 2);
+        assert_eq!(Pdu::Invalid { ident: 7 }.ident(), u64::MAX);
+    }
+
+    #[test]
+    fn pdu_ident_matches_encoded_frame() {
+        let pdu = Pdu::KillPane(KillPane { pane_id: 3 });
+        let mut encoded = Vec::new();
+        pdu.encode(&mut encoded, 9).unwrap();
+        let decoded = decode_raw(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.ident, pdu.ident());
+    }
+
     // --- Pdu::pane_id tests ---
 
     #[test]
@@ -1637,172 +4216,1417 @@ mod test {
     #[test]
     fn pdu_roundtrip_unit_response() {
         let mut buf = Vec::new();
-        let pdu = Pdu::UnitResponse(UnitResponse {});
-        pdu.encode(&mut buf, 200).unwrap();
+        let pdu = Pdu::UnitResponse(UnitResponse {});
+        pdu.encode(&mut buf, 200).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 200);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_get_codec_version() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetCodecVersion(GetCodecVersion {});
+        pdu.encode(&mut buf, 300).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 300);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_write_to_pane() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::WriteToPane(WriteToPane {
+            pane_id: 5,
+            data: b"hello world".to_vec(),
+        });
+        pdu.encode(&mut buf, 400).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 400);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_send_paste() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::SendPaste(SendPaste {
+            pane_id: 3,
+            data: "clipboard text".into(),
+        });
+        pdu.encode(&mut buf, 500).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 500);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_kill_pane() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::KillPane(KillPane { pane_id: 99 });
+        pdu.encode(&mut buf, 600).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 600);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_pane_removed() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::PaneRemoved(PaneRemoved { pane_id: 42 });
+        pdu.encode(&mut buf, 700).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 700);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_tab_resized() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::TabResized(TabResized { tab_id: 11 });
+        pdu.encode(&mut buf, 800).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 800);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_pane_focused() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::PaneFocused(PaneFocused { pane_id: 77 });
+        pdu.encode(&mut buf, 900).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 900);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    #[test]
+    fn pdu_roundtrip_rename_workspace() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::RenameWorkspace(RenameWorkspace {
+            old_workspace: "old".into(),
+            new_workspace: "new".into(),
+        });
+        pdu.encode(&mut buf, 1000).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 1000);
+        assert_eq!(decoded.pdu, pdu);
+    }
+
+    // --- Pdu::encode Invalid should fail ---
+
+    #[test]
+    fn pdu_encode_invalid_fails() {
+        let mut buf = Vec::new();
+        let result = Pdu::Invalid { ident: 0 }.encode(&mut buf, 0);
+        assert!(result.is_err());
+    }
+
+    // --- stream_decode edge cases ---
+
+    #[test]
+    fn stream_decode_empty_buffer() {
+        let mut buffer = Vec::new();
+        let result = Pdu::stream_decode(&mut buffer).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn stream_decode_partial_frame() {
+        // Just the length byte, no payload
+        let mut buffer = vec![2u8];
+        let result = Pdu::stream_decode(&mut buffer).unwrap();
+        assert!(result.is_none());
+        // Buffer should be preserved for future reads
+        assert_eq!(buffer, vec![2u8]);
+    }
+
+    #[test]
+    fn stream_decode_consumes_one_frame() {
+        let mut encoded = Vec::new();
+        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
+        Pdu::Pong(Pong {}).encode(&mut encoded, 2).unwrap();
+        let total_len = encoded.len();
+
+        let decoded = Pdu::stream_decode(&mut encoded).unwrap().unwrap();
+        assert_eq!(decoded.pdu, Pdu::Ping(Ping {}));
+        assert_eq!(decoded.serial, 1);
+        // Buffer should still contain the Pong frame
+        assert!(encoded.len() < total_len);
+
+        let decoded2 = Pdu::stream_decode(&mut encoded).unwrap().unwrap();
+        assert_eq!(decoded2.pdu, Pdu::Pong(Pong {}));
+        assert_eq!(decoded2.serial, 2);
+        assert!(encoded.is_empty());
+    }
+
+    // --- SerializedLines tests ---
+
+    #[test]
+    fn serialized_lines_default_empty() {
+        let sl = SerializedLines::default();
+        let (lines, images) = sl.extract_data();
+        assert!(lines.is_empty());
+        assert!(images.is_empty());
+    }
+
+    #[test]
+    fn serialized_lines_from_empty_vec() {
+        let sl: SerializedLines = vec![].into();
+        let (lines, images) = sl.extract_data();
+        assert!(lines.is_empty());
+        assert!(images.is_empty());
+    }
+
+    fn hyperlinked_line() -> Line {
+        let mut line = Line::from_text("abcdefgh", &Default::default(), 1, None);
+        let link = Arc::new(Hyperlink::new("https://example.com"));
+        for cell in &mut line.cells_mut_for_attr_changes_only()[2..5] {
+            cell.attrs_mut().set_hyperlink(Some(Arc::clone(&link)));
+        }
+        line
+    }
+
+    fn linked_columns(line: &Line) -> Vec<usize> {
+        line.visible_cells()
+            .filter(|cell| cell.attrs().hyperlink().is_some())
+            .map(|cell| cell.cell_index())
+            .collect()
+    }
+
+    #[test]
+    fn serialized_lines_whole_line_without_cols() {
+        let sl = SerializedLines::with_column_range(vec![(0, hyperlinked_line())], None);
+        let (lines, _) = sl.extract_data();
+        assert_eq!(lines[0].1.as_str(), "abcdefgh");
+        assert_eq!(linked_columns(&lines[0].1), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn serialized_lines_trims_to_column_range() {
+        let sl = SerializedLines::with_column_range(vec![(7, hyperlinked_line())], Some(3..7));
+        let (lines, images) = sl.extract_data();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].0, 7);
+        assert_eq!(lines[0].1.as_str(), "defg");
+        // Cells 3 and 4 of the original line carried the link
+        assert_eq!(linked_columns(&lines[0].1), vec![0, 1]);
+        assert!(images.is_empty());
+    }
+
+    #[test]
+    fn serialized_lines_column_range_past_end_is_clamped() {
+        let sl = SerializedLines::with_column_range(vec![(0, hyperlinked_line())], Some(6..20));
+        let (lines, _) = sl.extract_data();
+        assert_eq!(lines[0].1.as_str(), "gh");
+        assert!(linked_columns(&lines[0].1).is_empty());
+    }
+
+    /// `GetLines` as it was before `cols` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct GetLinesWithoutCols {
+        pane_id: PaneId,
+        lines: Vec<Range<StableRowIndex>>,
+    }
+
+    #[test]
+    fn get_lines_decodes_from_older_client() {
+        let old = GetLinesWithoutCols {
+            pane_id: 3,
+            lines: vec![0..10, 20..25],
+        };
+        let new = GetLines {
+            pane_id: 3,
+            lines: vec![0..10, 20..25],
+            cols: None,
+        };
+        assert_forward_compatible(&old, &new);
+    }
+
+    #[test]
+    fn pdu_roundtrip_get_lines_with_column_range() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetLines(GetLines {
+            pane_id: 3,
+            lines: vec![0..10, 20..25],
+            cols: Some(40..120),
+        });
+        pdu.encode(&mut buf, 11).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 11);
+        assert_eq!(decoded.pdu, pdu);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetLinesResponse(GetLinesResponse {
+            pane_id: 3,
+            lines: SerializedLines::with_column_range(vec![(20, hyperlinked_line())], Some(3..7)),
+        });
+        pdu.encode(&mut buf, 12).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        match decoded.pdu {
+            Pdu::GetLinesResponse(resp) => {
+                let (lines, _) = resp.lines.extract_data();
+                assert_eq!(lines[0].1.as_str(), "defg");
+                assert_eq!(linked_columns(&lines[0].1), vec![0, 1]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    // --- SetPaneInputLocked tests ---
+
+    #[test]
+    fn pdu_roundtrip_set_pane_input_locked() {
+        for (serial, locked) in [(62, true), (63, false)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::SetPaneInputLocked(SetPaneInputLocked { pane_id: 5, locked });
+            pdu.encode(&mut buf, serial).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, serial);
+            assert_eq!(decoded.pdu, pdu);
+            assert_eq!(decoded.pdu.pane_id(), Some(5));
+            assert!(decoded.pdu.is_user_input());
+            assert!(!decoded.pdu.is_response());
+            assert!(!decoded.pdu.is_notification());
+        }
+    }
+
+    // --- SetPaneWorkingDir tests ---
+
+    #[test]
+    fn pdu_roundtrip_set_pane_working_dir() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::SetPaneWorkingDir(SetPaneWorkingDir {
+            pane_id: 6,
+            cwd: "file://host/home/user/project"
+                .to_string()
+                .try_into()
+                .unwrap(),
+        });
+        pdu.encode(&mut buf, 61).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 61);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(6));
+        assert!(decoded.pdu.is_user_input());
+        assert!(!decoded.pdu.is_response());
+        match decoded.pdu {
+            Pdu::SetPaneWorkingDir(SetPaneWorkingDir { cwd, .. }) => {
+                assert_eq!(cwd.url.path(), "/home/user/project");
+            }
+            other => panic!("unexpected pdu {:?}", other),
+        }
+    }
+
+    // --- ReattachPane tests ---
+
+    #[test]
+    fn pdu_roundtrip_reattach_pane() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::ReattachPane(ReattachPane {
+            pane_id: 12,
+            client_id: ClientId::new(),
+        });
+        pdu.encode(&mut buf, 50).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 50);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(12));
+        assert!(!decoded.pdu.is_response());
+    }
+
+    // --- CancelRequest tests ---
+
+    #[test]
+    fn pdu_roundtrip_cancel_request() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::CancelRequest(CancelRequest { serial: 47 });
+        pdu.encode(&mut buf, 49).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 49);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pdu_name(), "CancelRequest");
+        assert_eq!(decoded.pdu.ident(), 89);
+        assert!(!decoded.pdu.is_response());
+        assert!(!decoded.pdu.is_notification());
+        assert!(!decoded.pdu.is_user_input());
+    }
+
+    // --- EraseScrollbackRequest tests ---
+
+    #[test]
+    fn pdu_roundtrip_erase_scrollback_range() {
+        for (serial, range) in [(47, Some(-500..120)), (48, None)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::EraseScrollbackRequest(EraseScrollbackRequest {
+                pane_id: 2,
+                erase_mode: ScrollbackEraseMode::ScrollbackOnly,
+                range,
+            });
+            pdu.encode(&mut buf, serial).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, serial);
+            assert_eq!(decoded.pdu, pdu);
+        }
+    }
+
+    /// `EraseScrollbackRequest` as it was before `range` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct EraseScrollbackRequestWithoutRange {
+        pane_id: PaneId,
+        erase_mode: ScrollbackEraseMode,
+    }
+
+    #[test]
+    fn erase_scrollback_decodes_from_older_client() {
+        let old = EraseScrollbackRequestWithoutRange {
+            pane_id: 2,
+            erase_mode: ScrollbackEraseMode::ScrollbackAndViewport,
+        };
+        let new = EraseScrollbackRequest {
+            pane_id: 2,
+            erase_mode: ScrollbackEraseMode::ScrollbackAndViewport,
+            range: None,
+        };
+        assert_forward_compatible(&old, &new);
+    }
+
+    // --- GetLogicalLines tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_logical_lines() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetLogicalLines(GetLogicalLines {
+            pane_id: 4,
+            range: -20..5,
+        });
+        pdu.encode(&mut buf, 53).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 53);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetLogicalLinesResponse(GetLogicalLinesResponse {
+            pane_id: 4,
+            lines: vec![
+                "a long line that was soft wrapped across rows".to_string(),
+                String::new(),
+                "$ ls".to_string(),
+            ],
+        });
+        pdu.encode(&mut buf, 54).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 54);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(4));
+        assert!(decoded.pdu.is_response());
+    }
+
+    // --- GetLinesChunked tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_lines_chunked_reassembles() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetLinesChunked(GetLinesChunked {
+            pane_id: 3,
+            lines: vec![0..7],
+            chunk_rows: 3,
+        });
+        pdu.encode(&mut buf, 46).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 46);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let texts: Vec<String> = (0..7).map(|i| format!("line {}", i)).collect();
+        let lines: Vec<(StableRowIndex, Line)> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                (
+                    i as StableRowIndex,
+                    Line::from_text(text, &Default::default(), 1, None),
+                )
+            })
+            .collect();
+        let chunks = GetLinesChunkResponse::split(3, lines, 3);
+        assert_eq!(chunks.len(), 3);
+
+        let mut buf = Vec::new();
+        for chunk in chunks {
+            Pdu::GetLinesChunkResponse(chunk)
+                .encode(&mut buf, 46)
+                .unwrap();
+        }
+        let mut buf = buf.as_slice();
+        let mut reassembled = Vec::new();
+        let mut last_flags = Vec::new();
+        for _ in 0..3 {
+            let decoded = Pdu::decode(&mut buf).unwrap();
+            assert_eq!(decoded.serial, 46);
+            assert_eq!(decoded.pdu.pane_id(), Some(3));
+            assert!(decoded.pdu.is_response());
+            match decoded.pdu {
+                Pdu::GetLinesChunkResponse(chunk) => {
+                    last_flags.push(chunk.is_last);
+                    let (lines, _) = chunk.lines.extract_data();
+                    reassembled.extend(lines);
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(last_flags, vec![false, false, true]);
+        let rows: Vec<StableRowIndex> = reassembled.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, (0..7).collect::<Vec<_>>());
+        let got: Vec<String> = reassembled
+            .iter()
+            .map(|(_, line)| line.as_str().trim_end().to_string())
+            .collect();
+        assert_eq!(got, texts);
+    }
+
+    #[test]
+    fn get_lines_chunk_split_edge_cases() {
+        let chunks = GetLinesChunkResponse::split(1, vec![], 10);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].is_last);
+
+        let line = |i| (i, Line::from_text("x", &Default::default(), 1, None));
+        let chunks = GetLinesChunkResponse::split(1, vec![line(0), line(1)], 0);
+        assert_eq!(chunks.len(), 2);
+        let chunks = GetLinesChunkResponse::split(1, vec![line(0), line(1)], 2);
+        assert_eq!(chunks.len(), 1);
+        assert!(chunks[0].is_last);
+    }
+
+    // --- GetCursorPosition tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_cursor_position() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetCursorPosition(GetCursorPosition { pane_id: 4 });
+        pdu.encode(&mut buf, 64).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 64);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetCursorPositionResponse(GetCursorPositionResponse {
+            pane_id: 4,
+            cursor_position: StableCursorPosition {
+                x: 12,
+                y: 340,
+                ..Default::default()
+            },
+            seqno: 77,
+        });
+        pdu.encode(&mut buf, 64).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 64);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(4));
+        assert!(decoded.pdu.is_response());
+        assert!(!decoded.pdu.is_notification());
+    }
+
+    // --- GetPaneRenderChanges tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_pane_render_changes_since_seqno() {
+        for since_seqno in [None, Some(0), Some(12345)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::GetPaneRenderChanges(GetPaneRenderChanges {
+                pane_id: 9,
+                since_seqno,
+                want_bonus_lines: true,
+            });
+            pdu.encode(&mut buf, 14).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, 14);
+            assert_eq!(decoded.pdu, pdu);
+        }
+    }
+
+    /// `GetPaneRenderChanges` as it was before `since_seqno` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct GetPaneRenderChangesWithoutSeqno {
+        pane_id: PaneId,
+    }
+
+    #[test]
+    fn get_pane_render_changes_decodes_from_older_client() {
+        let old = GetPaneRenderChangesWithoutSeqno { pane_id: 4 };
+        assert_forward_compatible(&old, &GetPaneRenderChanges::new(4));
+    }
+
+    #[test]
+    fn get_pane_render_changes_honours_want_bonus_lines() {
+        for want_bonus_lines in [true, false] {
+            let mut req = GetPaneRenderChanges::new(3);
+            req.want_bonus_lines = want_bonus_lines;
+
+            let mut buf = Vec::new();
+            let pdu = Pdu::GetPaneRenderChanges(req);
+            pdu.encode(&mut buf, 57).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.pdu, pdu);
+            let req = match decoded.pdu {
+                Pdu::GetPaneRenderChanges(req) => req,
+                other => panic!("unexpected pdu {:?}", other),
+            };
+
+            let line = Line::from_text("bonus", &Default::default(), 1, None);
+            let resp = GetPaneRenderChangesResponse {
+                pane_id: req.pane_id,
+                mouse_grabbed: false,
+                cursor_position: StableCursorPosition::default(),
+                dimensions: RenderableDimensions {
+                    cols: 80,
+                    viewport_rows: 24,
+                    scrollback_rows: 0,
+                    physical_top: 0,
+                    scrollback_top: 0,
+                    dpi: 96,
+                    pixel_width: 0,
+                    pixel_height: 0,
+                    reverse_video: false,
+                },
+                dirty_lines: vec![],
+                title: "title".to_string(),
+                working_dir: None,
+                bonus_lines: req.bonus_lines(vec![(0, line)]),
+                input_serial: None,
+                seqno: 1,
+            };
+
+            let mut buf = Vec::new();
+            let pdu = Pdu::GetPaneRenderChangesResponse(resp);
+            pdu.encode(&mut buf, 58).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.pdu, pdu);
+            match decoded.pdu {
+                Pdu::GetPaneRenderChangesResponse(resp) => {
+                    let lines = resp.bonus_lines.extract_data().0;
+                    assert_eq!(lines.is_empty(), !want_bonus_lines);
+                }
+                other => panic!("unexpected pdu {:?}", other),
+            }
+        }
+    }
+
+    /// `GetPaneRenderChanges` as it was before `want_bonus_lines`
+    /// was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct GetPaneRenderChangesWithoutBonusFlag {
+        pane_id: PaneId,
+        since_seqno: Option<SequenceNo>,
+    }
+
+    #[test]
+    fn get_pane_render_changes_from_older_client_wants_bonus_lines() {
+        let old = GetPaneRenderChangesWithoutBonusFlag {
+            pane_id: 4,
+            since_seqno: Some(17),
+        };
+        let new = GetPaneRenderChanges {
+            pane_id: 4,
+            since_seqno: Some(17),
+            want_bonus_lines: true,
+        };
+        assert_forward_compatible(&old, &new);
+    }
+
+    // --- GetScrollbackLineCount tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_scrollback_line_count() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetScrollbackLineCount(GetScrollbackLineCount { pane_id: 4 });
+        pdu.encode(&mut buf, 15).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 15);
+        assert_eq!(decoded.pdu, pdu);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetScrollbackLineCountResponse(GetScrollbackLineCountResponse {
+            pane_id: 4,
+            first_row: -20,
+            count: 3500,
+        });
+        pdu.encode(&mut buf, 16).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 16);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(4));
+        assert_eq!(decoded.pdu.pdu_name(), "GetScrollbackLineCountResponse");
+    }
+
+    // --- GetServerInfo tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_server_info() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetServerInfo(GetServerInfo {});
+        pdu.encode(&mut buf, 24).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 24);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetServerInfoResponse(GetServerInfoResponse {
+            version_string: "20260101-000000-deadbeef".to_string(),
+            codec_vers: CODEC_VERSION,
+            uptime_secs: 86_400 * 3 + 17,
+            client_count: 2,
+            pane_count: 9,
+        });
+        pdu.encode(&mut buf, 25).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 25);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pdu_name(), "GetServerInfoResponse");
+        assert!(decoded.pdu.is_response());
+        assert!(!decoded.pdu.is_notification());
+        assert_eq!(decoded.pdu.pane_id(), None);
+    }
+
+    // --- GetPaneNeighbors tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_pane_neighbors() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetPaneNeighbors(GetPaneNeighbors { pane_id: 5 });
+        pdu.encode(&mut buf, 44).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 44);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetPaneNeighborsResponse(GetPaneNeighborsResponse {
+            pane_id: 5,
+            up: Some(2),
+            down: None,
+            left: Some(4),
+            right: None,
+        });
+        pdu.encode(&mut buf, 45).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 45);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(5));
+        assert!(decoded.pdu.is_response());
+        assert!(!decoded.pdu.is_notification());
+    }
+
+    // --- AttachWorkspace / DetachWorkspace tests ---
+
+    #[test]
+    fn pdu_roundtrip_attach_detach_workspace() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::AttachWorkspace(AttachWorkspace {
+            workspace: "coding".to_string(),
+        });
+        pdu.encode(&mut buf, 40).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 40);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pdu_name(), "AttachWorkspace");
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::DetachWorkspace(DetachWorkspace {
+            workspace: "coding".to_string(),
+        });
+        pdu.encode(&mut buf, 41).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 41);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pdu_name(), "DetachWorkspace");
+        assert!(!decoded.pdu.is_notification());
+    }
+
+    #[test]
+    fn attach_workspace_pdu_idents() {
+        let attach = Pdu::AttachWorkspace(AttachWorkspace {
+            workspace: String::new(),
+        });
+        let detach = Pdu::DetachWorkspace(DetachWorkspace {
+            workspace: String::new(),
+        });
+        assert_eq!(attach.ident(), 83);
+        assert_eq!(detach.ident(), 84);
+    }
+
+    // --- SpawnV2 / SplitPane env tests ---
+
+    #[test]
+    fn pdu_roundtrip_spawn_with_env() {
+        let mut env = HashMap::new();
+        env.insert("EDITOR".to_string(), "vim".to_string());
+        env.insert("RUST_LOG".to_string(), "debug".to_string());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::SpawnV2(SpawnV2 {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: Some(1),
+            command: Some(CommandBuilder::new("bash")),
+            command_dir: Some("/tmp".to_string()),
+            size: TerminalSize::default(),
+            workspace: "default".to_string(),
+            env: env.clone(),
+        });
+        pdu.encode(&mut buf, 36).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 36);
+        assert_eq!(decoded.pdu, pdu);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::SplitPane(SplitPane {
+            pane_id: 3,
+            split_request: SplitRequest::default(),
+            command: None,
+            command_dir: None,
+            domain: config::keyassignment::SpawnTabDomain::CurrentPaneDomain,
+            move_pane_id: None,
+            env,
+        });
+        pdu.encode(&mut buf, 37).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 37);
+        assert_eq!(decoded.pdu, pdu);
+        match decoded.pdu {
+            Pdu::SplitPane(split) => {
+                assert_eq!(split.env.len(), 2);
+                assert_eq!(split.env["EDITOR"], "vim");
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
+    }
+
+    // --- WriteToPaneBulk tests ---
+
+    #[test]
+    fn pdu_roundtrip_write_to_pane_bulk() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::WriteToPaneBulk(WriteToPaneBulk {
+            pane_id: 13,
+            chunks: vec![b"l".to_vec(), b"s".to_vec(), vec![], b" -la\r".to_vec()],
+        });
+        pdu.encode(&mut buf, 34).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 34);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(13));
+        assert!(decoded.pdu.is_user_input());
+        assert!(!decoded.pdu.is_response());
+    }
+
+    #[test]
+    fn write_to_pane_bulk_applies_chunks_in_order() {
+        let keystrokes: Vec<Vec<u8>> = "echo hi\r".bytes().map(|b| vec![b]).collect();
+
+        // What the pane would see from one WriteToPane per keystroke
+        let individually = keystrokes.concat();
+
+        let mut buf = Vec::new();
+        Pdu::WriteToPaneBulk(WriteToPaneBulk {
+            pane_id: 1,
+            chunks: keystrokes,
+        })
+        .encode(&mut buf, 35)
+        .unwrap();
+        let bulk = match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::WriteToPaneBulk(bulk) => bulk,
+            pdu => panic!("unexpected {:?}", pdu),
+        };
+        let mut pane = Vec::new();
+        bulk.write_to(&mut pane).unwrap();
+        assert_eq!(pane, individually);
+        assert_eq!(pane, b"echo hi\r");
+    }
+
+    // --- GetSelectionText tests ---
+
+    #[test]
+    fn pdu_roundtrip_get_selection_text() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetSelectionText(GetSelectionText { pane_id: 8 });
+        pdu.encode(&mut buf, 30).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 30);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        for (serial, text) in [(31, Some("echo hello\nworld".to_string())), (32, None)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::GetSelectionTextResponse(GetSelectionTextResponse { pane_id: 8, text });
+            pdu.encode(&mut buf, serial).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, serial);
+            assert_eq!(decoded.pdu, pdu);
+            assert_eq!(decoded.pdu.pane_id(), Some(8));
+            assert!(decoded.pdu.is_response());
+        }
+    }
+
+    // --- PingV2 tests ---
+
+    #[test]
+    fn pdu_roundtrip_ping_v2_echoes_token() {
+        let ping = PingV2::now();
+        let token = ping.token;
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::PingV2(ping);
+        pdu.encode(&mut buf, 59).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 59);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+        let pong = match decoded.pdu {
+            Pdu::PingV2(ping) => ping.reply(),
+            other => panic!("unexpected pdu {:?}", other),
+        };
+
+        let mut buf = Vec::new();
+        Pdu::PongV2(pong).encode(&mut buf, 59).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert!(decoded.pdu.is_response());
+        match decoded.pdu {
+            Pdu::PongV2(pong) => {
+                assert_eq!(pong.token, token);
+                assert!(pong.elapsed_millis() < 60_000);
+            }
+            other => panic!("unexpected pdu {:?}", other),
+        }
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::PongV2(PongV2 { token: u64::MAX });
+        pdu.encode(&mut buf, 60).unwrap();
+        assert_eq!(Pdu::decode(buf.as_slice()).unwrap().pdu, pdu);
+    }
+
+    // --- BellRang tests ---
+
+    #[test]
+    fn pdu_roundtrip_bell_rang() {
+        for (serial, visual) in [(55, false), (56, true)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::BellRang(BellRang { pane_id: 9, visual });
+            pdu.encode(&mut buf, serial).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, serial);
+            assert_eq!(decoded.pdu, pdu);
+            assert_eq!(decoded.pdu.pane_id(), Some(9));
+            assert!(decoded.pdu.is_notification());
+            assert!(!decoded.pdu.is_response());
+            assert_eq!(EventKind::of(&decoded.pdu), Some(EventKind::BellRang));
+        }
+    }
+
+    // --- NotifyProgress tests ---
+
+    #[test]
+    fn pdu_roundtrip_notify_progress() {
+        for (serial, fraction) in [(28, Some(0.375)), (29, None)] {
+            let mut buf = Vec::new();
+            let pdu = Pdu::NotifyProgress(NotifyProgress {
+                pane_id: 6,
+                label: "cargo build".to_string(),
+                fraction,
+            });
+            pdu.encode(&mut buf, serial).unwrap();
+            let decoded = Pdu::decode(buf.as_slice()).unwrap();
+            assert_eq!(decoded.serial, serial);
+            assert_eq!(decoded.pdu, pdu);
+            assert_eq!(decoded.pdu.pane_id(), Some(6));
+            assert!(decoded.pdu.is_notification());
+            assert!(!decoded.pdu.is_response());
+            assert_eq!(EventKind::of(&decoded.pdu), Some(EventKind::NotifyProgress));
+        }
+    }
+
+    // --- Hello tests ---
+
+    #[test]
+    fn pdu_roundtrip_hello() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::Hello(Hello {
+            codec_vers: CODEC_VERSION,
+            features: FeatureSet::COMPRESSION_ZSTD | FeatureSet::BATCH,
+        });
+        pdu.encode(&mut buf, 26).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 26);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(!decoded.pdu.is_response());
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::HelloResponse(HelloResponse {
+            codec_vers: CODEC_VERSION,
+            features: FeatureSet::all(),
+            heartbeat_interval_secs: 5,
+            heartbeat_timeout_secs: 20,
+            server_epoch: 1_700_000_000_000_000_000,
+        });
+        pdu.encode(&mut buf, 27).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 27);
+        assert_eq!(decoded.pdu, pdu);
+        assert!(decoded.pdu.is_response());
+        assert!(!decoded.pdu.is_notification());
+    }
+
+    #[test]
+    fn hello_response_heartbeat_defaults() {
+        let resp = HelloResponse::default();
+        assert_eq!(resp.codec_vers, CODEC_VERSION);
+        assert_eq!(resp.features, FeatureSet::empty());
+        assert_eq!(resp.heartbeat_interval_secs, 30);
+        assert_eq!(resp.heartbeat_timeout_secs, 90);
+        // A peer should get several chances to respond before
+        // being considered dead
+        assert!(resp.heartbeat_timeout_secs >= 2 * resp.heartbeat_interval_secs);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::HelloResponse(resp);
+        pdu.encode(&mut buf, 33).unwrap();
+        match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::HelloResponse(decoded) => {
+                assert_eq!(decoded.heartbeat_interval_secs, 30);
+                assert_eq!(decoded.heartbeat_timeout_secs, 90);
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn server_epoch_roundtrips_and_is_stable() {
+        let epoch = server_epoch();
+        assert_ne!(epoch, 0);
+        assert_eq!(server_epoch(), epoch);
+        assert_eq!(HelloResponse::default().server_epoch, epoch);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::GetCodecVersionResponse(GetCodecVersionResponse {
+            codec_vers: CODEC_VERSION,
+            version_string: "test".into(),
+            executable_path: PathBuf::from("/bin/test"),
+            config_file_path: None,
+            server_epoch: epoch,
+        });
+        pdu.encode(&mut buf, 42).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 42);
+        match decoded.pdu {
+            Pdu::GetCodecVersionResponse(resp) => assert_eq!(resp.server_epoch, epoch),
+            pdu => panic!("unexpected {:?}", pdu),
+        }
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::HelloResponse(HelloResponse {
+            server_epoch: u64::MAX,
+            ..HelloResponse::default()
+        });
+        pdu.encode(&mut buf, 43).unwrap();
+        match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::HelloResponse(resp) => assert_eq!(resp.server_epoch, u64::MAX),
+            pdu => panic!("unexpected {:?}", pdu),
+        }
+    }
+
+    #[test]
+    fn feature_set_negotiate_intersects() {
+        let client = FeatureSet::COMPRESSION_ZSTD | FeatureSet::FRAME_SEQUENCE;
+        let server = FeatureSet::COMPRESSION_ZSTD | FeatureSet::BATCH;
+        assert_eq!(client.negotiate(server), FeatureSet::COMPRESSION_ZSTD);
+        assert_eq!(server.negotiate(client), FeatureSet::COMPRESSION_ZSTD);
+        assert_eq!(client.negotiate(FeatureSet::empty()), FeatureSet::empty());
+        assert_eq!(FeatureSet::default(), FeatureSet::empty());
+
+        // Features from some future codec version are never in play
+        let unknown = unsafe { FeatureSet::from_bits_unchecked(1 << 40) };
+        let peer = FeatureSet::BATCH | unknown;
+        assert_eq!(peer.negotiate(peer), FeatureSet::BATCH);
+    }
+
+    // --- SendRawKey tests ---
+
+    #[test]
+    fn pdu_roundtrip_send_raw_key() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::SendRawKey(SendRawKey {
+            pane_id: 12,
+            bytes: b"\x1b[A:wq\r".to_vec(),
+            input_serial: InputSerial::from(std::time::SystemTime::UNIX_EPOCH),
+        });
+        pdu.encode(&mut buf, 23).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 23);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(12));
+        assert!(decoded.pdu.is_user_input());
+    }
+
+    // --- ListTabs tests ---
+
+    #[test]
+    fn pdu_roundtrip_list_tabs() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::ListTabs(ListTabs { window_id: Some(2) });
+        pdu.encode(&mut buf, 21).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 21);
+        assert_eq!(decoded.pdu, pdu);
+
+        let mut buf = Vec::new();
+        let pdu = Pdu::ListTabsResponse(ListTabsResponse {
+            tabs: vec![
+                TabInfo {
+                    tab_id: 1,
+                    title: "vim".to_string(),
+                    is_active: false,
+                    pane_count: 1,
+                },
+                TabInfo {
+                    tab_id: 4,
+                    title: "build".to_string(),
+                    is_active: true,
+                    pane_count: 3,
+                },
+            ],
+        });
+        pdu.encode(&mut buf, 22).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 200);
+        assert_eq!(decoded.serial, 22);
         assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), None);
     }
 
+    // --- GetPaneFontInfo tests ---
+
     #[test]
-    fn pdu_roundtrip_get_codec_version() {
+    fn pdu_roundtrip_get_pane_font_info() {
         let mut buf = Vec::new();
-        let pdu = Pdu::GetCodecVersion(GetCodecVersion {});
-        pdu.encode(&mut buf, 300).unwrap();
+        let pdu = Pdu::GetPaneFontInfo(GetPaneFontInfo { pane_id: 5 });
+        pdu.encode(&mut buf, 19).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 300);
+        assert_eq!(decoded.serial, 19);
         assert_eq!(decoded.pdu, pdu);
-    }
 
-    #[test]
-    fn pdu_roundtrip_write_to_pane() {
         let mut buf = Vec::new();
-        let pdu = Pdu::WriteToPane(WriteToPane {
+        let pdu = Pdu::GetPaneFontInfoResponse(GetPaneFontInfoResponse {
             pane_id: 5,
-            data: b"hello world".to_vec(),
+            cell_width: 9,
+            cell_height: 19,
+            dpi: 144,
         });
-        pdu.encode(&mut buf, 400).unwrap();
+        pdu.encode(&mut buf, 20).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 400);
+        assert_eq!(decoded.serial, 20);
         assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(5));
+    }
+
+    // --- TerminalSize pixel dimension tests ---
+
+    /// A size whose every field differs from `TerminalSize::default()`,
+    /// so that a field dropped from serialization can't go unnoticed
+    fn hidpi_size() -> TerminalSize {
+        TerminalSize {
+            rows: 40,
+            cols: 100,
+            pixel_width: 2400,
+            pixel_height: 1920,
+            dpi: 192,
+        }
     }
 
     #[test]
-    fn pdu_roundtrip_send_paste() {
+    fn pdu_roundtrip_resize_keeps_pixel_dimensions() {
         let mut buf = Vec::new();
-        let pdu = Pdu::SendPaste(SendPaste {
-            pane_id: 3,
-            data: "clipboard text".into(),
+        let pdu = Pdu::Resize(Resize {
+            containing_tab_id: 1,
+            pane_id: 2,
+            size: hidpi_size(),
         });
-        pdu.encode(&mut buf, 500).unwrap();
-        let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 500);
-        assert_eq!(decoded.pdu, pdu);
+        pdu.encode(&mut buf, 51).unwrap();
+        match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::Resize(resize) => {
+                assert_eq!(resize.size.pixel_width, 2400);
+                assert_eq!(resize.size.pixel_height, 1920);
+                assert_eq!(resize.size.dpi, 192);
+                assert_eq!(resize.size, hidpi_size());
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
     }
 
     #[test]
-    fn pdu_roundtrip_kill_pane() {
+    fn pdu_roundtrip_spawn_keeps_pixel_dimensions() {
         let mut buf = Vec::new();
-        let pdu = Pdu::KillPane(KillPane { pane_id: 99 });
-        pdu.encode(&mut buf, 600).unwrap();
-        let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 600);
-        assert_eq!(decoded.pdu, pdu);
+        let pdu = Pdu::SpawnV2(SpawnV2 {
+            domain: config::keyassignment::SpawnTabDomain::DefaultDomain,
+            window_id: None,
+            command: None,
+            command_dir: None,
+            size: hidpi_size(),
+            workspace: "default".to_string(),
+            env: HashMap::new(),
+        });
+        pdu.encode(&mut buf, 52).unwrap();
+        match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::SpawnV2(spawn) => {
+                assert_eq!(spawn.size.pixel_width, 2400);
+                assert_eq!(spawn.size.pixel_height, 1920);
+                assert_eq!(spawn.size, hidpi_size());
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
     }
 
+    // --- ResizeTab tests ---
+
     #[test]
-    fn pdu_roundtrip_pane_removed() {
+    fn pdu_roundtrip_resize_tab() {
         let mut buf = Vec::new();
-        let pdu = Pdu::PaneRemoved(PaneRemoved { pane_id: 42 });
-        pdu.encode(&mut buf, 700).unwrap();
+        let pdu = Pdu::ResizeTab(ResizeTab {
+            tab_id: 7,
+            size: TerminalSize {
+                rows: 50,
+                cols: 132,
+                pixel_width: 1320,
+                pixel_height: 1000,
+                dpi: 96,
+            },
+        });
+        pdu.encode(&mut buf, 17).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 700);
+        assert_eq!(decoded.serial, 17);
         assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pdu_name(), "ResizeTab");
+        assert_eq!(decoded.pdu.pane_id(), None);
     }
 
+    // --- SetClientId tests ---
+
     #[test]
-    fn pdu_roundtrip_tab_resized() {
+    fn pdu_roundtrip_set_client_id_capabilities() {
         let mut buf = Vec::new();
-        let pdu = Pdu::TabResized(TabResized { tab_id: 11 });
-        pdu.encode(&mut buf, 800).unwrap();
+        let pdu = Pdu::SetClientId(SetClientId {
+            client_id: ClientId::new(),
+            is_proxy: true,
+            capabilities: ClientCapabilities::IMAGES | ClientCapabilities::PROGRESS,
+        });
+        pdu.encode(&mut buf, 38).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 800);
+        assert_eq!(decoded.serial, 38);
         assert_eq!(decoded.pdu, pdu);
+        match decoded.pdu {
+            Pdu::SetClientId(set) => {
+                assert!(set.capabilities.contains(ClientCapabilities::IMAGES));
+                assert!(!set.capabilities.contains(ClientCapabilities::RENDER_DELTAS));
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
     }
 
     #[test]
-    fn pdu_roundtrip_pane_focused() {
+    fn set_client_id_default_capabilities_are_legacy() {
+        assert_eq!(ClientCapabilities::default(), ClientCapabilities::empty());
+
         let mut buf = Vec::new();
-        let pdu = Pdu::PaneFocused(PaneFocused { pane_id: 77 });
-        pdu.encode(&mut buf, 900).unwrap();
-        let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 900);
-        assert_eq!(decoded.pdu, pdu);
+        let pdu = Pdu::SetClientId(SetClientId {
+            client_id: ClientId::new(),
+            is_proxy: false,
+            capabilities: ClientCapabilities::default(),
+        });
+        pdu.encode(&mut buf, 39).unwrap();
+        match Pdu::decode(buf.as_slice()).unwrap().pdu {
+            Pdu::SetClientId(set) => {
+                assert!(set.capabilities.is_empty());
+                assert!(!set.is_proxy);
+            }
+            pdu => panic!("unexpected {:?}", pdu),
+        }
+    }
+
+    /// `SetClientId` as it was before `capabilities` was added
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct LegacySetClientId {
+        client_id: ClientId,
+        is_proxy: bool,
     }
 
     #[test]
-    fn pdu_roundtrip_rename_workspace() {
+    fn set_client_id_decodes_from_legacy_client() {
+        let client_id = ClientId::new();
+        let old = LegacySetClientId {
+            client_id: client_id.clone(),
+            is_proxy: true,
+        };
+        let new = SetClientId {
+            client_id,
+            is_proxy: true,
+            capabilities: ClientCapabilities::empty(),
+        };
+        assert_forward_compatible(&old, &new);
+
+        let (data, is_compressed) = serialize(&old).unwrap();
         let mut buf = Vec::new();
-        let pdu = Pdu::RenameWorkspace(RenameWorkspace {
-            old_workspace: "old".into(),
-            new_workspace: "new".into(),
-        });
-        pdu.encode(&mut buf, 1000).unwrap();
-        let decoded = Pdu::decode(buf.as_slice()).unwrap();
-        assert_eq!(decoded.serial, 1000);
-        assert_eq!(decoded.pdu, pdu);
+        encode_raw(40, 39, &data, is_compressed, &mut buf).unwrap();
+        assert_eq!(
+            Pdu::decode(buf.as_slice()).unwrap().pdu,
+            Pdu::SetClientId(new)
+        );
     }
 
-    // --- Pdu::encode Invalid should fail ---
+    // --- Batch tests ---
 
     #[test]
-    fn pdu_encode_invalid_fails() {
+    fn pdu_roundtrip_batch() {
+        let set_client_id = SetClientId {
+            client_id: ClientId::new(),
+            is_proxy: false,
+            capabilities: ClientCapabilities::default(),
+        };
+        let pdu = Pdu::Batch(Batch {
+            requests: vec![
+                Pdu::GetCodecVersion(GetCodecVersion {}),
+                Pdu::SetClientId(set_client_id),
+                Pdu::ListPanes(ListPanes {}),
+            ],
+        });
         let mut buf = Vec::new();
-        let result = Pdu::Invalid { ident: 0 }.encode(&mut buf, 0);
-        assert!(result.is_err());
-    }
+        pdu.encode(&mut buf, 15).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 15);
+        assert_eq!(decoded.pdu, pdu);
 
-    // --- stream_decode edge cases ---
+        match decoded.pdu {
+            Pdu::Batch(batch) => {
+                let names: Vec<_> = batch.requests.iter().map(Pdu::pdu_name).collect();
+                assert_eq!(names, vec!["GetCodecVersion", "SetClientId", "ListPanes"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 
     #[test]
-    fn stream_decode_empty_buffer() {
-        let mut buffer = Vec::new();
-        let result = Pdu::stream_decode(&mut buffer).unwrap();
-        assert!(result.is_none());
+    fn pdu_roundtrip_batch_response() {
+        let pdu = Pdu::BatchResponse(BatchResponse {
+            responses: vec![
+                Pdu::UnitResponse(UnitResponse {}),
+                Pdu::ErrorResponse(ErrorResponse {
+                    reason: "no such pane".into(),
+                }),
+            ],
+        });
+        let mut buf = Vec::new();
+        pdu.encode(&mut buf, 16).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.pdu, pdu);
     }
 
     #[test]
-    fn stream_decode_partial_frame() {
-        // Just the length byte, no payload
-        let mut buffer = vec![2u8];
-        let result = Pdu::stream_decode(&mut buffer).unwrap();
-        assert!(result.is_none());
-        // Buffer should be preserved for future reads
-        assert_eq!(buffer, vec![2u8]);
+    fn batch_cannot_be_nested_when_encoding() {
+        let pdu = Pdu::Batch(Batch {
+            requests: vec![Pdu::Batch(Batch::default())],
+        });
+        let mut buf = Vec::new();
+        assert!(pdu.encode(&mut buf, 1).is_err());
     }
 
     #[test]
-    fn stream_decode_consumes_one_frame() {
+    fn batch_cannot_be_nested_when_decoding() {
+        #[derive(Serialize)]
+        struct RawBatch {
+            requests: Vec<Vec<u8>>,
+        }
+
+        let mut inner = Vec::new();
+        Pdu::BatchResponse(BatchResponse::default())
+            .encode(&mut inner, 0)
+            .unwrap();
+        let (data, is_compressed) = serialize(&RawBatch {
+            requests: vec![inner],
+        })
+        .unwrap();
         let mut encoded = Vec::new();
-        Pdu::Ping(Ping {}).encode(&mut encoded, 1).unwrap();
-        Pdu::Pong(Pong {}).encode(&mut encoded, 2).unwrap();
-        let total_len = encoded.len();
+        encode_raw(64, 1, &data, is_compressed, &mut encoded).unwrap();
 
-        let decoded = Pdu::stream_decode(&mut encoded).unwrap().unwrap();
-        assert_eq!(decoded.pdu, Pdu::Ping(Ping {}));
-        assert_eq!(decoded.serial, 1);
-        // Buffer should still contain the Pong frame
-        assert!(encoded.len() < total_len);
+        let err = Pdu::decode(encoded.as_slice()).expect_err("nested batch");
+        assert!(
+            format!("{:#}", err).contains("cannot contain another batch"),
+            "{:#}",
+            err
+        );
+    }
 
-        let decoded2 = Pdu::stream_decode(&mut encoded).unwrap().unwrap();
-        assert_eq!(decoded2.pdu, Pdu::Pong(Pong {}));
-        assert_eq!(decoded2.serial, 2);
-        assert!(encoded.is_empty());
+    // --- SubscribeEvents tests ---
+
+    #[test]
+    fn pdu_roundtrip_subscribe_events() {
+        let mut buf = Vec::new();
+        let pdu = Pdu::SubscribeEvents(SubscribeEvents {
+            events: vec![
+                EventKind::WindowTitleChanged,
+                EventKind::WindowWorkspaceChanged,
+            ],
+        });
+        pdu.encode(&mut buf, 13).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 13);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(pdu.ident(), 63);
+        assert_eq!(pdu.pdu_name(), "SubscribeEvents");
     }
 
-    // --- SerializedLines tests ---
+    #[test]
+    fn subscribe_events_default_is_all() {
+        let sub = SubscribeEvents::default();
+        assert_eq!(sub.events, EventKind::ALL);
+        assert!(sub.wants(&Pdu::TabResized(TabResized { tab_id: 1 })));
+        assert!(sub.wants(&Pdu::PaneFocused(PaneFocused { pane_id: 2 })));
+    }
 
     #[test]
-    fn serialized_lines_default_empty() {
-        let sl = SerializedLines::default();
-        let (lines, images) = sl.extract_data();
-        assert!(lines.is_empty());
-        assert!(images.is_empty());
+    fn subscribe_events_filters_push_notifications() {
+        let sub = SubscribeEvents {
+            events: vec![EventKind::WindowTitleChanged],
+        };
+        assert!(sub.wants(&Pdu::WindowTitleChanged(WindowTitleChanged {
+            window_id: 1,
+            title: "status".into(),
+        })));
+        assert!(!sub.wants(&Pdu::TabResized(TabResized { tab_id: 1 })));
+        assert!(!sub.wants(&Pdu::NotifyAlert(NotifyAlert {
+            pane_id: 1,
+            alert: Alert::Bell,
+        })));
+        // Responses are never filtered
+        assert!(sub.wants(&Pdu::Pong(Pong {})));
+        assert!(sub.wants(&Pdu::UnitResponse(UnitResponse {})));
     }
 
     #[test]
-    fn serialized_lines_from_empty_vec() {
-        let sl: SerializedLines = vec![].into();
-        let (lines, images) = sl.extract_data();
-        assert!(lines.is_empty());
-        assert!(images.is_empty());
+    fn event_kind_of_non_notification() {
+        assert_eq!(EventKind::of(&Pdu::Ping(Ping {})), None);
+        assert_eq!(
+            EventKind::of(&Pdu::PaneRemoved(PaneRemoved { pane_id: 4 })),
+            Some(EventKind::PaneRemoved)
+        );
     }
 
     // --- CODEC_VERSION test ---
 
     #[test]
     fn codec_version_is_current() {
-        assert_eq!(CODEC_VERSION, 45);
+        assert_eq!(CODEC_VERSION, 78);
     }
 
     // --- CorruptResponse tests ---
@@ -1827,6 +5651,7 @@ mod test {
     fn decoded_pdu_debug() {
         let dp = DecodedPdu {
             serial: 42,
+            frame_seq: None,
             pdu: Pdu::Ping(Ping {}),
         };
         let dbg = format!("{:?}", dp);
@@ -1838,14 +5663,17 @@ mod test {
     fn decoded_pdu_partial_eq() {
         let a = DecodedPdu {
             serial: 1,
+            frame_seq: None,
             pdu: Pdu::Ping(Ping {}),
         };
         let b = DecodedPdu {
             serial: 1,
+            frame_seq: None,
             pdu: Pdu::Ping(Ping {}),
         };
         let c = DecodedPdu {
             serial: 2,
+            frame_seq: None,
             pdu: Pdu::Ping(Ping {}),
         };
         assert_eq!(a, b);
@@ -1871,8 +5699,9 @@ mod test {
             version_string: "1.0.0".into(),
             executable_path: PathBuf::from("/usr/bin/ft"),
             config_file_path: Some(PathBuf::from("/etc/ft.toml")),
+            server_epoch: 7,
         };
-        assert_eq!(resp.codec_vers, 45);
+        assert_eq!(resp.codec_vers, 78);
         assert_eq!(resp.version_string, "1.0.0");
     }
 
@@ -2024,6 +5853,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn frame_len_accepts_representable_lengths() {
+        if usize::BITS == 64 {
+            assert_eq!(
+                frame_len(!COMPRESSED_MASK, "test").unwrap() as u64,
+                !COMPRESSED_MASK
+            );
+        }
+        assert_eq!(frame_len(usize::MAX as u64, "test").unwrap(), usize::MAX);
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn decode_raw_rejects_length_exceeding_usize() {
+        let mut header = vec![];
+        leb128::write::unsigned(&mut header, 1 << 40).unwrap();
+        leb128::write::unsigned(&mut header, 1).unwrap();
+        leb128::write::unsigned(&mut header, 1).unwrap();
+        let err = decode_raw(header.as_slice()).expect_err("expected oversized frame to fail");
+        assert!(
+            err.downcast_ref::<CorruptResponse>().is_some(),
+            "unexpected error: {:#}",
+            err
+        );
+        assert!(err.to_string().contains("exceeds the maximum"));
+    }
+
     #[test]
     fn deserialize_invalid_compressed_payload_errors() {
         let err =
@@ -2037,12 +5893,159 @@ mod test {
     #[test]
     fn serialize_with_mode_always_compresses_small_payload() {
         let (payload, is_compressed) =
-            serialize_with_mode(&7u8, CompressionMode::Always).expect("serialize");
+            serialize_with_mode(&7u8, CompressionMode::Always, "test").expect("serialize");
         assert!(is_compressed);
         let roundtrip: u8 = deserialize(payload.as_slice(), true).expect("deserialize");
         assert_eq!(roundtrip, 7u8);
     }
 
+    #[test]
+    fn stream_compressed_pings_are_smaller_and_decode_in_order() {
+        let pings: Vec<(u64, Pdu)> = (1..=50)
+            .map(|serial| (serial, Pdu::Ping(Ping {})))
+            .collect();
+
+        let mut individual = Vec::new();
+        for (serial, pdu) in &pings {
+            pdu.encode(&mut individual, *serial).unwrap();
+        }
+
+        let mut envelope = Vec::new();
+        Pdu::encode_stream_compressed(
+            pings.iter().map(|(serial, pdu)| (*serial, pdu)),
+            &mut envelope,
+        )
+        .unwrap();
+        assert!(
+            envelope.len() < individual.len(),
+            "envelope {} vs individual {}",
+            envelope.len(),
+            individual.len()
+        );
+
+        let decoded = Pdu::decode_frames(envelope.as_slice()).unwrap();
+        assert_eq!(decoded.len(), 50);
+        for (decoded, (serial, pdu)) in decoded.iter().zip(&pings) {
+            assert_eq!(decoded.serial, *serial);
+            assert_eq!(&decoded.pdu, pdu);
+        }
+    }
+
+    #[test]
+    fn decode_frames_passes_plain_frames_through() {
+        let mut encoded = Vec::new();
+        Pdu::Pong(Pong {}).encode(&mut encoded, 9).unwrap();
+        let decoded = Pdu::decode_frames(encoded.as_slice()).unwrap();
+        assert_eq!(
+            decoded,
+            vec![DecodedPdu {
+                serial: 9,
+                frame_seq: None,
+                pdu: Pdu::Pong(Pong {})
+            }]
+        );
+
+        let mut envelope = Vec::new();
+        encode_raw(STREAM_ENVELOPE_IDENT, 0, b"junk", false, &mut envelope).unwrap();
+        assert!(Pdu::decode_frames(envelope.as_slice()).is_err());
+    }
+
+    fn stream_envelope_of(pdus: &[(u64, Pdu)]) -> Vec<u8> {
+        let mut envelope = Vec::new();
+        Pdu::encode_stream_compressed(
+            pdus.iter().map(|(serial, pdu)| (*serial, pdu)),
+            &mut envelope,
+        )
+        .unwrap();
+        envelope
+    }
+
+    #[test]
+    fn stream_decode_expands_envelope() {
+        let pdus: Vec<(u64, Pdu)> = (1..=3).map(|serial| (serial, Pdu::Ping(Ping {}))).collect();
+        let mut buffer = stream_envelope_of(&pdus);
+        Pdu::Pong(Pong {}).encode(&mut buffer, 4).unwrap();
+
+        for (serial, pdu) in &pdus {
+            let decoded = Pdu::stream_decode(&mut buffer).unwrap().unwrap();
+            assert_eq!(decoded.serial, *serial);
+            assert_eq!(&decoded.pdu, pdu);
+        }
+        let decoded = Pdu::stream_decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(decoded.serial, 4);
+        assert_eq!(decoded.pdu, Pdu::Pong(Pong {}));
+        assert!(buffer.is_empty());
+        assert!(Pdu::stream_decode(&mut buffer).unwrap().is_none());
+    }
+
+    #[test]
+    fn stream_decode_waits_for_complete_envelope() {
+        let envelope = stream_envelope_of(&[(1, Pdu::Ping(Ping {}))]);
+        let mut buffer = envelope[..envelope.len() - 1].to_vec();
+        assert!(Pdu::stream_decode(&mut buffer).unwrap().is_none());
+        assert_eq!(buffer, envelope[..envelope.len() - 1]);
+
+        buffer.push(*envelope.last().unwrap());
+        let decoded = Pdu::stream_decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(decoded.pdu, Pdu::Ping(Ping {}));
+    }
+
+    #[test]
+    fn pdu_stream_decoder_expands_envelopes() {
+        let pdus: Vec<(u64, Pdu)> = (1..=5).map(|serial| (serial, Pdu::Ping(Ping {}))).collect();
+        let mut stream = stream_envelope_of(&pdus[..2]);
+        stream.extend(stream_envelope_of(&pdus[2..]));
+
+        let decoded: Vec<DecodedPdu> = PduStreamDecoder::new(stream.as_slice())
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(decoded.len(), pdus.len());
+        for (decoded, (serial, pdu)) in decoded.iter().zip(&pdus) {
+            assert_eq!(decoded.serial, *serial);
+            assert_eq!(&decoded.pdu, pdu);
+        }
+    }
+
+    #[test]
+    fn single_frame_decoders_reject_envelopes() {
+        let envelope = stream_envelope_of(&[(1, Pdu::Ping(Ping {}))]);
+        let err = Pdu::decode(envelope.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("stream envelope"), "{:#}", err);
+        assert!(Pdu::decode_from_slice(&envelope).is_err());
+        #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+        smol::block_on(async {
+            let mut reader = smol::io::Cursor::new(envelope.clone());
+            assert!(Pdu::decode_async(&mut reader, None).await.is_err());
+        });
+    }
+
+    #[test]
+    fn nested_stream_envelope_is_rejected() {
+        let inner = stream_envelope_of(&[(1, Pdu::Ping(Ping {}))]);
+        let compressed = zstd::encode_all(inner.as_slice(), 0).unwrap();
+        let mut outer = Vec::new();
+        encode_raw(STREAM_ENVELOPE_IDENT, 0, &compressed, true, &mut outer).unwrap();
+
+        assert!(Pdu::decode_frames(outer.as_slice()).is_err());
+        let mut buffer = outer.clone();
+        assert!(Pdu::stream_decode(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn truncated_frame_within_envelope_is_rejected() {
+        let mut frames = Vec::new();
+        Pdu::Pong(Pong {}).encode(&mut frames, 1).unwrap();
+        frames.pop();
+        let compressed = zstd::encode_all(frames.as_slice(), 0).unwrap();
+        let mut envelope = Vec::new();
+        encode_raw(STREAM_ENVELOPE_IDENT, 0, &compressed, true, &mut envelope).unwrap();
+        // The frame that follows must not be taken as the missing byte
+        Pdu::Ping(Ping {}).encode(&mut envelope, 2).unwrap();
+
+        assert!(Pdu::stream_decode(&mut envelope).is_err());
+    }
+
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
     #[test]
     fn encode_raw_async_roundtrip_uncompressed() {
         smol::block_on(async {
@@ -2060,6 +6063,7 @@ mod test {
         });
     }
 
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
     #[test]
     fn decode_raw_async_roundtrip_uncompressed() {
         smol::block_on(async {
@@ -2067,7 +6071,7 @@ mod test {
             encode_raw(11, 13, b"decode-async", false, &mut encoded).expect("encode_raw");
 
             let mut reader = smol::io::Cursor::new(encoded);
-            let decoded = decode_raw_async(&mut reader, None)
+            let decoded = decode_raw_async(&mut reader, None, None)
                 .await
                 .expect("decode_raw_async");
             assert_eq!(decoded.ident, 11);
@@ -2077,6 +6081,7 @@ mod test {
         });
     }
 
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
     #[test]
     fn decode_raw_async_roundtrip_compressed_flag() {
         smol::block_on(async {
@@ -2084,7 +6089,7 @@ mod test {
             encode_raw(31, 9, b"decode-async-compressed", true, &mut encoded).expect("encode_raw");
 
             let mut reader = smol::io::Cursor::new(encoded);
-            let decoded = decode_raw_async(&mut reader, None)
+            let decoded = decode_raw_async(&mut reader, None, None)
                 .await
                 .expect("decode_raw_async");
             assert_eq!(decoded.ident, 31);
@@ -2094,6 +6099,7 @@ mod test {
         });
     }
 
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
     #[test]
     fn decode_raw_async_rejects_serial_over_max() {
         smol::block_on(async {
@@ -2101,7 +6107,7 @@ mod test {
             encode_raw(3, 99, b"x", false, &mut encoded).expect("encode_raw");
 
             let mut reader = smol::io::Cursor::new(encoded);
-            let err = decode_raw_async(&mut reader, Some(10))
+            let err = decode_raw_async(&mut reader, Some(10), None)
                 .await
                 .expect_err("serial should be rejected");
             let message = err.to_string();
@@ -2113,11 +6119,12 @@ mod test {
         });
     }
 
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
     #[test]
     fn read_u64_async_returns_eof_on_empty_input() {
         smol::block_on(async {
             let mut reader = smol::io::Cursor::new(Vec::<u8>::new());
-            let err = read_u64_async(&mut reader)
+            let err = read_u64_async(&mut reader, None)
                 .await
                 .expect_err("empty stream should error");
             let io_err = err
@@ -2127,6 +6134,124 @@ mod test {
         });
     }
 
+    /// An async reader that yields its data and then stalls forever
+    /// without ever waking the task, simulating a half-open peer.
+    #[derive(Debug)]
+    struct StallingReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl StallingReader {
+        /// Returns the next chunk of up to `max` bytes, or `None` once
+        /// the reader has stalled
+        fn next_chunk(&mut self, max: usize) -> Option<&[u8]> {
+            let start = self.pos;
+            let n = (self.data.len() - start).min(max);
+            if n == 0 {
+                return None;
+            }
+            self.pos += n;
+            Some(&self.data[start..start + n])
+        }
+    }
+
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+    impl smol::io::AsyncRead for StallingReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut [u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            match self.next_chunk(buf.len()) {
+                Some(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    std::task::Poll::Ready(Ok(chunk.len()))
+                }
+                None => std::task::Poll::Pending,
+            }
+        }
+    }
+
+    #[cfg(feature = "async-asupersync")]
+    impl AsyncRead for StallingReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+            buf: &mut asupersync::io::ReadBuf<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            match self.next_chunk(buf.remaining()) {
+                Some(chunk) => {
+                    buf.put_slice(chunk);
+                    std::task::Poll::Ready(Ok(()))
+                }
+                None => std::task::Poll::Pending,
+            }
+        }
+    }
+
+    async fn assert_stalled_frame_times_out() {
+        let mut encoded = Vec::new();
+        Pdu::WriteToPane(WriteToPane {
+            pane_id: 1,
+            data: b"stalled".to_vec(),
+        })
+        .encode(&mut encoded, 5)
+        .unwrap();
+        // Deliver only the length header; the rest never arrives
+        let mut reader = StallingReader {
+            data: encoded[..1].to_vec(),
+            pos: 0,
+        };
+
+        let err = Pdu::decode_async_with_timeout(
+            &mut reader,
+            None,
+            Some(std::time::Duration::from_millis(50)),
+        )
+        .await
+        .expect_err("stalled frame should time out");
+        let io_err = err
+            .root_cause()
+            .downcast_ref::<std::io::Error>()
+            .expect("expected io::Error");
+        assert_eq!(io_err.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+    #[test]
+    fn decode_async_with_timeout_fires_on_stalled_frame() {
+        smol::block_on(assert_stalled_frame_times_out());
+    }
+
+    #[cfg(feature = "async-asupersync")]
+    #[test]
+    fn decode_async_with_timeout_fires_on_stalled_frame_asupersync() {
+        let runtime = asupersync::runtime::RuntimeBuilder::current_thread()
+            .build()
+            .expect("build asupersync runtime");
+        runtime.block_on(assert_stalled_frame_times_out());
+    }
+
+    #[cfg(all(feature = "async-smol", not(feature = "async-asupersync")))]
+    #[test]
+    fn decode_async_with_timeout_decodes_complete_frame() {
+        smol::block_on(async {
+            let mut encoded = Vec::new();
+            Pdu::Ping(Ping {}).encode(&mut encoded, 6).unwrap();
+            let mut reader = smol::io::Cursor::new(encoded);
+            let decoded = Pdu::decode_async_with_timeout(
+                &mut reader,
+                None,
+                Some(std::time::Duration::from_secs(5)),
+            )
+            .await
+            .unwrap();
+            assert_eq!(decoded.serial, 6);
+            assert_eq!(decoded.pdu, Pdu::Ping(Ping {}));
+        });
+    }
+
     // --- Additional PDU roundtrip tests (wa-2tcrj) ---
 
     #[test]
@@ -2170,6 +6295,7 @@ mod test {
             version_string: "test".into(),
             executable_path: PathBuf::from("/bin/test"),
             config_file_path: None,
+            server_epoch: 1,
         });
         pdu.encode(&mut buf, 444).unwrap();
         let decoded = Pdu::decode(buf.as_slice()).unwrap();
@@ -2278,6 +6404,25 @@ mod test {
         );
     }
 
+    #[test]
+    fn pdu_roundtrip_set_clipboard_v2_binary() {
+        let png = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff];
+        let pdu = Pdu::SetClipboardV2(SetClipboardV2 {
+            pane_id: 56,
+            mime_type: "image/png".to_string(),
+            data: Some(png),
+            selection: ClipboardSelection::Clipboard,
+        });
+        let mut buf = Vec::new();
+        pdu.encode(&mut buf, 18).unwrap();
+        let decoded = Pdu::decode(buf.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 18);
+        assert_eq!(decoded.pdu, pdu);
+        assert_eq!(decoded.pdu.pane_id(), Some(56));
+        assert!(decoded.pdu.is_user_input());
+        assert_eq!(EventKind::of(&decoded.pdu), Some(EventKind::SetClipboard));
+    }
+
     #[test]
     fn pdu_pane_id_list_panes_is_none() {
         assert_eq!(Pdu::ListPanes(ListPanes {}).pane_id(), None);
@@ -51,6 +51,7 @@ fn stream_decode_unknown_ident_consumes_frame() {
         decoded,
         DecodedPdu {
             serial: 1,
+            frame_seq: None,
             pdu: Pdu::Invalid { ident: 99 }
         }
     );
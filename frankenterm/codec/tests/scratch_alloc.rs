@@ -0,0 +1,123 @@
+//! Tests for the scratch buffers that `Pdu::encode` serializes into.
+//! They live in their own test binary because counting allocations
+//! means replacing the global allocator.
+
+use std::cell::Cell;
+
+use codec::{CompressionMode, Pdu, Ping, WriteToPane};
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts the allocations made by each thread, so that tests can
+/// check how many a given code path makes.
+struct CountingAllocator;
+
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made while encoding `pdu`
+/// into `frame`, which must already have room for it.
+fn encode_allocations(pdu: &Pdu, frame: &mut Vec<u8>) -> usize {
+    frame.clear();
+    let before = ALLOCATIONS.with(Cell::get);
+    pdu.encode_with_mode(&mut *frame, 1, CompressionMode::Never)
+        .unwrap();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn encode_frames_straight_from_scratch() {
+    // Ping has an empty payload, so copying it would cost nothing;
+    // it measures the fixed overhead of encoding, such as metrics
+    let ping = Pdu::Ping(Ping {});
+    let small = Pdu::WriteToPane(WriteToPane {
+        pane_id: 1,
+        data: vec![3; 8],
+    });
+    let large = Pdu::WriteToPane(WriteToPane {
+        pane_id: 1,
+        data: vec![3; 4096],
+    });
+    let mut frame = Vec::with_capacity(8192);
+
+    // Warm up the scratch space
+    for pdu in [&ping, &small, &large] {
+        encode_allocations(pdu, &mut frame);
+    }
+    let overhead = encode_allocations(&ping, &mut frame);
+
+    for _ in 0..3 {
+        // A small frame is assembled on the stack
+        assert_eq!(encode_allocations(&small, &mut frame), overhead);
+        // A large one needs a single buffer for the frame
+        assert_eq!(encode_allocations(&large, &mut frame), overhead + 1);
+    }
+}
+
+#[test]
+fn serialize_scratch_reuse_is_deterministic() {
+    let big = Pdu::WriteToPane(WriteToPane {
+        pane_id: 1,
+        data: b"scratch".repeat(64),
+    });
+    let small = Pdu::WriteToPane(WriteToPane {
+        pane_id: 2,
+        data: b"hi".to_vec(),
+    });
+
+    let encode = |pdu: &Pdu| {
+        let mut buf = Vec::new();
+        pdu.encode(&mut buf, 1).unwrap();
+        buf
+    };
+    let first = encode(&big);
+    let small_bytes = encode(&small);
+    assert_eq!(encode(&big), first);
+    assert_eq!(encode(&small), small_bytes);
+    assert_eq!(Pdu::decode(first.as_slice()).unwrap().pdu, big);
+    assert_eq!(Pdu::decode(small_bytes.as_slice()).unwrap().pdu, small);
+}
+
+#[test]
+fn serialize_scratch_is_per_thread() {
+    let pdu_for = |n: u8| {
+        Pdu::WriteToPane(WriteToPane {
+            pane_id: n as usize,
+            data: vec![n; 40 + n as usize * 100],
+        })
+    };
+    let handles: Vec<_> = (1..=2u8)
+        .map(|n| {
+            std::thread::spawn(move || {
+                let pdu = pdu_for(n);
+                let mut expected = Vec::new();
+                pdu.encode(&mut expected, n as u64).unwrap();
+                for _ in 0..200 {
+                    let mut buf = Vec::new();
+                    pdu.encode(&mut buf, n as u64).unwrap();
+                    assert_eq!(buf, expected);
+                }
+                expected
+            })
+        })
+        .collect();
+    for (n, handle) in (1..=2u8).zip(handles) {
+        let encoded = handle.join().unwrap();
+        let decoded = Pdu::decode(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.serial, n as u64);
+        assert_eq!(decoded.pdu, pdu_for(n));
+    }
+}
@@ -0,0 +1,23 @@
+//! Generates the sRGB to linear lookup table at build time, so that
+//! `no_std` builds get the same table lookups as `std` builds rather
+//! than evaluating `powf` for every conversion.
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+fn main() {
+    let mut table = String::from("[\n");
+    for val in 0..=255u8 {
+        let c = (val as f32) / 255.0;
+        let linear = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+        writeln!(table, "    {:?}f32,", linear).unwrap();
+    }
+    table.push(']');
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
+    std::fs::write(out_dir.join("srgb8_to_linear.rs"), table).unwrap();
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -23,11 +23,12 @@ use std::sync::LazyLock;
 extern crate alloc;
 
 use alloc::format;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
-#[cfg(feature = "std")]
-static SRGB_TO_F32_TABLE: LazyLock<[f32; 256]> = LazyLock::new(generate_srgb8_to_linear_f32_table);
+/// Maps srgb u8 to linear f32; generated by build.rs so that it is
+/// available without `std`
+static SRGB_TO_F32_TABLE: [f32; 256] = include!(concat!(env!("OUT_DIR"), "/srgb8_to_linear.rs"));
 #[cfg(feature = "std")]
 static F32_TO_U8_TABLE: LazyLock<[u32; 104]> = LazyLock::new(generate_linear_f32_to_srgb8_table);
 #[cfg(feature = "std")]
@@ -54,20 +55,6 @@ fn generate_rgb_to_linear_f32_table() -> [f32; 256] {
     table
 }
 
-#[cfg(feature = "std")]
-fn generate_srgb8_to_linear_f32_table() -> [f32; 256] {
-    let mut table = [0.; 256];
-    for (val, entry) in table.iter_mut().enumerate() {
-        let c = (val as f32) / 255.0;
-        *entry = if c <= 0.04045 {
-            c / 12.92
-        } else {
-            ((c + 0.055) / 1.055).powf(2.4)
-        };
-    }
-    table
-}
-
 #[allow(clippy::unreadable_literal)]
 #[cfg(feature = "std")]
 fn generate_linear_f32_to_srgb8_table() -> [u32; 104] {
@@ -162,6 +149,12 @@ pub fn linear_u8_to_srgb8(f: u8) -> u8 {
 
 #[cfg(feature = "std")]
 fn linear_f32_to_srgb8_using_table(f: f32) -> u8 {
+    linear_f32_to_srgb8_with_table(f, &F32_TO_U8_TABLE)
+}
+
+#[cfg(feature = "std")]
+#[inline(always)]
+fn linear_f32_to_srgb8_with_table(f: f32, table: &[u32; 104]) -> u8 {
     #[allow(clippy::unreadable_literal)]
     const ALMOST_ONE: u32 = 0x3f7fffff;
     #[allow(clippy::unreadable_literal)]
@@ -178,7 +171,7 @@ fn linear_f32_to_srgb8_using_table(f: f32) -> u8 {
     };
 
     let f_bits = f.to_bits();
-    let tab = unsafe { *F32_TO_U8_TABLE.get_unchecked(((f_bits - MINVAL) >> 20) as usize) };
+    let tab = unsafe { *table.get_unchecked(((f_bits - MINVAL) >> 20) as usize) };
     let bias = (tab >> 16) << 9;
     let scale = tab & 0xffff;
 
@@ -200,18 +193,59 @@ fn linear_f32_to_srgb8(f: f32) -> u8 {
 
 /// Convert from srgb in u8 0-255 to linear floating point rgb 0-1.0
 fn srgb8_to_linear_f32(val: u8) -> f32 {
-    #[cfg(feature = "std")]
-    {
-        return unsafe { *SRGB_TO_F32_TABLE.get_unchecked(val as usize) };
+    SRGB_TO_F32_TABLE[val as usize]
+}
+
+/// Returns the table used to convert srgb u8 values to linear
+/// floating point rgb 0-1.0, indexed by the srgb value.
+/// It is computed at build time, so it is available without `std`.
+pub fn srgb8_to_linear_table() -> &'static [f32; 256] {
+    &SRGB_TO_F32_TABLE
+}
+
+/// Convert a single srgb channel value (0-1.0) to linear (0-1.0)
+/// using the sRGB transfer function directly, without the lookup
+/// tables or any loss of precision from quantizing to u8.
+pub fn srgb_f32_to_linear(v: f32) -> f32 {
+    // See https://docs.rs/palette/0.5.0/src/palette/encoding/srgb.rs.html#43
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
     }
-    #[cfg(not(feature = "std"))]
-    {
-        let c = (val as f32) / 255.0;
-        if c <= 0.04045 {
-            c / 12.92
-        } else {
-            ((c + 0.055) / 1.055).powf(2.4)
-        }
+}
+
+/// Convert a single linear channel value (0-1.0) to srgb (0-1.0).
+/// This is the inverse of `srgb_f32_to_linear`.
+pub fn linear_to_srgb_f32(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        v.powf(1.0 / 2.4) * 1.055 - 0.055
+    }
+}
+
+/// Convert a slice of srgb u8 values to linear floating point rgb 0-1.0,
+/// storing the results in `dst`, which must be the same length as `src`.
+/// The lookup table is resolved once up front, leaving a tight loop
+/// that is cheaper than calling the per-value conversion in a loop.
+pub fn srgb8_slice_to_linear(src: &[u8], dst: &mut [f32]) {
+    debug_assert_eq!(src.len(), dst.len());
+    let table: &[f32; 256] = &SRGB_TO_F32_TABLE;
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = table[*s as usize];
+    }
+}
+
+/// Convert a slice of linear floating point rgb 0-1.0 values to srgb u8,
+/// storing the results in `dst`, which must be the same length as `src`.
+/// This is the inverse of `srgb8_slice_to_linear`.
+#[cfg(feature = "std")]
+pub fn linear_slice_to_srgb8(src: &[f32], dst: &mut [u8]) {
+    debug_assert_eq!(src.len(), dst.len());
+    let table: &[u32; 104] = &F32_TO_U8_TABLE;
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d = linear_f32_to_srgb8_with_table(*s, table);
     }
 }
 
@@ -266,6 +300,19 @@ impl SrgbaPixel {
         self.0
     }
 
+    /// Create a pixel from bytes in R, G, B, A order, as used by
+    /// RGBA8 texture uploads, regardless of platform endianness
+    pub fn from_rgba_bytes([red, green, blue, alpha]: [u8; 4]) -> Self {
+        Self::rgba(red, green, blue, alpha)
+    }
+
+    /// Returns the pixel as bytes in R, G, B, A order, regardless of
+    /// platform endianness
+    pub fn to_rgba_bytes(self) -> [u8; 4] {
+        let (r, g, b, a) = self.as_rgba();
+        [r, g, b, a]
+    }
+
     pub fn as_srgba_tuple(self) -> (f32, f32, f32, f32) {
         let u8tuple = self.as_rgba();
         let SrgbaTuple(r, g, b, a) = u8tuple.into();
@@ -273,6 +320,58 @@ impl SrgbaPixel {
     }
 }
 
+/// Returns the average relative luminance of `pixels`, from 0.0
+/// (black) to 1.0 (white); eg: to judge the overall brightness of a
+/// rendered region.  Alpha is ignored.  An empty buffer yields 0.0.
+#[cfg(feature = "std")]
+pub fn mean_luminance(pixels: &[SrgbaPixel]) -> f32 {
+    if pixels.is_empty() {
+        return 0.;
+    }
+    let total: f32 = pixels
+        .iter()
+        .map(|p| p.to_linear().relative_luminance())
+        .sum();
+    total / pixels.len() as f32
+}
+
+/// Counts `pixels` into 16 equal-width buckets by relative
+/// luminance, with black in the first bucket and white in the last.
+#[cfg(feature = "std")]
+pub fn luminance_histogram(pixels: &[SrgbaPixel]) -> [usize; 16] {
+    let mut buckets = [0; 16];
+    for p in pixels {
+        let lum = p.to_linear().relative_luminance().clamp(0., 1.);
+        buckets[((lum * 16.) as usize).min(15)] += 1;
+    }
+    buckets
+}
+
+/// The formula used by `SrgbaTuple::delta_e_method` to compute
+/// the perceptual difference between two colors
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeltaEMethod {
+    /// Plain euclidean distance in Lab space; the cheapest to compute
+    Cie76,
+    /// CIE94, weighted for graphic arts
+    Cie94,
+    /// CIEDE2000; the most accurate, but also the slowest
+    #[default]
+    Ciede2000,
+}
+
+#[cfg(feature = "std")]
+impl From<DeltaEMethod> for deltae::DEMethod {
+    fn from(method: DeltaEMethod) -> Self {
+        match method {
+            DeltaEMethod::Cie76 => deltae::DEMethod::DE1976,
+            DeltaEMethod::Cie94 => deltae::DEMethod::DE1994G,
+            DeltaEMethod::Ciede2000 => deltae::DEMethod::DE2000,
+        }
+    }
+}
+
 /// A pixel value encoded as SRGBA RGBA values in f32 format (range: 0.0-1.0)
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
@@ -322,6 +421,12 @@ impl SrgbaTuple {
 
         r.demultiply()
     }
+
+    /// Returns the inverse of this color, computed per RGB channel.
+    /// The alpha channel is preserved.
+    pub fn invert(&self) -> Self {
+        Self(1.0 - self.0, 1.0 - self.1, 1.0 - self.2, self.3)
+    }
 }
 
 impl ToDynamic for SrgbaTuple {
@@ -458,6 +563,28 @@ impl SrgbaTuple {
         }
     }
 
+    /// Parse an 8 digit hex color with the alpha first, `#AARRGGBB`,
+    /// as written by Android and some config formats.  CSS puts the
+    /// alpha last, so this ordering must be requested explicitly
+    /// rather than going through `from_str`.
+    /// The leading `#` is optional.  Other lengths are rejected, as
+    /// without an alpha component the ordering is ambiguous.
+    #[allow(clippy::result_unit_err)]
+    pub fn from_argb_hex(s: &str) -> Result<Self, ()> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 8 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(());
+        }
+        let word = u32::from_str_radix(hex, 16).map_err(|_| ())?;
+        let [alpha, red, green, blue] = word.to_be_bytes();
+        Ok(Self(
+            red as f32 / 255.,
+            green as f32 / 255.,
+            blue as f32 / 255.,
+            alpha as f32 / 255.,
+        ))
+    }
+
     /// Returns self multiplied by the supplied alpha value.
     /// We don't need to linearize for this, as alpha is defined
     /// as being linear even in srgba!
@@ -465,24 +592,56 @@ impl SrgbaTuple {
         Self(self.0, self.1, self.2, self.3 * alpha)
     }
 
+    /// Returns self with the alpha channel replaced by `alpha`,
+    /// clamped to 0.0-1.0, unlike `mul_alpha` which scales it.
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self(self.0, self.1, self.2, alpha.clamp(0., 1.))
+    }
+
+    /// Returns self with each channel clamped to 0.0-1.0, as color
+    /// math can push channels out of range, which would otherwise
+    /// wrap or truncate badly when converted to u8.
+    pub fn clamp(self) -> Self {
+        Self(
+            self.0.clamp(0., 1.),
+            self.1.clamp(0., 1.),
+            self.2.clamp(0., 1.),
+            self.3.clamp(0., 1.),
+        )
+    }
+
+    /// Returns true if every channel is within 0.0-1.0
+    pub fn is_in_gamut(&self) -> bool {
+        [self.0, self.1, self.2, self.3]
+            .iter()
+            .all(|c| (0. ..=1.).contains(c))
+    }
+
     pub fn to_linear(self) -> LinearRgba {
-        // See https://docs.rs/palette/0.5.0/src/palette/encoding/srgb.rs.html#43
-        fn to_linear(v: f32) -> f32 {
-            if v <= 0.04045 {
-                v / 12.92
-            } else {
-                ((v + 0.055) / 1.055).powf(2.4)
-            }
-        }
         // Note that alpha is always linear
         LinearRgba(
-            to_linear(self.0),
-            to_linear(self.1),
-            to_linear(self.2),
+            srgb_f32_to_linear(self.0),
+            srgb_f32_to_linear(self.1),
+            srgb_f32_to_linear(self.2),
             self.3,
         )
     }
 
+    /// Converts to linear and premultiplies by alpha in a single
+    /// step, as needed for blending.  Note that the premultiply is
+    /// applied to the linear channels, which is not the same as
+    /// `premultiply().to_linear()`.
+    /// `LinearRgba::to_srgb_straight` is the inverse.
+    pub fn to_linear_premultiplied(&self) -> LinearRgba {
+        let a = self.3;
+        LinearRgba(
+            srgb_f32_to_linear(self.0) * a,
+            srgb_f32_to_linear(self.1) * a,
+            srgb_f32_to_linear(self.2) * a,
+            a,
+        )
+    }
+
     pub fn to_srgb_u8(self) -> (u8, u8, u8, u8) {
         (
             (self.0 * 255.) as u8,
@@ -529,6 +688,34 @@ impl SrgbaTuple {
         )
     }
 
+    /// Returns a standards compliant CSS color string with integer
+    /// 0-255 channels: `rgb(R G B)` if opaque, `rgb(R G B / A)` otherwise.
+    /// Unlike `to_rgba_string`, this form is accepted by browsers.
+    pub fn to_css_string(self) -> String {
+        let (r, g, b) = (
+            (self.0 * 255.).round() as u8,
+            (self.1 * 255.).round() as u8,
+            (self.2 * 255.).round() as u8,
+        );
+        if self.3 == 1.0 {
+            format!("rgb({} {} {})", r, g, b)
+        } else {
+            format!("rgb({} {} {} / {})", r, g, b, self.3)
+        }
+    }
+
+    /// Returns a string of the form `rgb(R,G,B)` with integer 0-255
+    /// channels, as commonly used in terminal control sequences.
+    /// The alpha channel is not represented.
+    pub fn to_rgb_int_string(self) -> String {
+        format!(
+            "rgb({},{},{})",
+            (self.0 * 255.).round() as u8,
+            (self.1 * 255.).round() as u8,
+            (self.2 * 255.).round() as u8
+        )
+    }
+
     /// Returns a string of the form `rgb:RRRR/GGGG/BBBB`
     pub fn to_x11_16bit_rgb_string(self) -> String {
         format!(
@@ -587,6 +774,40 @@ impl SrgbaTuple {
         Self::from_hsla(h, s, l, a)
     }
 
+    /// Like `saturate`, but `factor` is clamped to the range -1.0 to
+    /// 1.0, so that an out of range factor can't overshoot and the
+    /// result is always a valid HSL color.
+    #[cfg(feature = "std")]
+    pub fn saturate_clamped(&self, factor: f64) -> Self {
+        self.saturate(factor.clamp(-1., 1.))
+    }
+
+    /// Like `lighten`, but `factor` is clamped to the range -1.0 to
+    /// 1.0, so that an out of range factor can't overshoot and the
+    /// result is always a valid HSL color.
+    #[cfg(feature = "std")]
+    pub fn lighten_clamped(&self, factor: f64) -> Self {
+        self.lighten(factor.clamp(-1., 1.))
+    }
+
+    /// Adjust the lightness by delta, which may be negative, in the
+    /// oklab color space.  Unlike the HSL based `lighten_fixed`, equal
+    /// steps look equally large regardless of hue.
+    /// The resulting lightness is clamped to the range 0.0 to 1.0.
+    #[cfg(feature = "std")]
+    pub fn adjust_lightness_oklab(&self, delta: f64) -> Self {
+        let [l, a, b, alpha] = self.to_linear().to_oklaba();
+        let l = (l as f64 + delta).clamp(0., 1.) as f32;
+        // Colors pushed outside of the sRGB gamut are clipped to it
+        let LinearRgba(r, g, b, alpha) = LinearRgba::from_oklaba(l, a, b, alpha);
+        Self(
+            linear_to_srgb_f32(r.clamp(0., 1.)),
+            linear_to_srgb_f32(g.clamp(0., 1.)),
+            linear_to_srgb_f32(b.clamp(0., 1.)),
+            alpha,
+        )
+    }
+
     /// Rotate the hue angle by the specified number of degrees
     #[cfg(feature = "std")]
     pub fn adjust_hue_fixed(&self, amount: f64) -> Self {
@@ -595,6 +816,14 @@ impl SrgbaTuple {
         Self::from_hsla(h, s, l, a)
     }
 
+    /// Flip the lightness of the color, keeping its hue and saturation,
+    /// so that a dark blue becomes a light blue
+    #[cfg(feature = "std")]
+    pub fn invert_lightness(&self) -> Self {
+        let (h, s, l, a) = self.to_hsla();
+        Self::from_hsla(h, s, 1.0 - l, a)
+    }
+
     #[cfg(feature = "std")]
     pub fn complement(&self) -> Self {
         self.adjust_hue_fixed(180.)
@@ -619,6 +848,49 @@ impl SrgbaTuple {
         )
     }
 
+    /// Like `triad`, but spaced evenly around the RYB color wheel,
+    /// which gives the harmonies that artists expect
+    #[cfg(feature = "std")]
+    pub fn triad_ryb(&self) -> (Self, Self) {
+        (
+            self.adjust_hue_fixed_ryb(120.),
+            self.adjust_hue_fixed_ryb(-120.),
+        )
+    }
+
+    /// Like `square`, but spaced evenly around the RYB color wheel
+    #[cfg(feature = "std")]
+    pub fn tetrad_ryb(&self) -> (Self, Self, Self) {
+        (
+            self.adjust_hue_fixed_ryb(90.),
+            self.adjust_hue_fixed_ryb(270.),
+            self.adjust_hue_fixed_ryb(180.),
+        )
+    }
+
+    /// Returns `n` opaque colors with hues spaced evenly around the
+    /// color wheel, starting at red, at the given `saturation` and
+    /// `lightness` (both 0.0-1.0); eg: to give each pane or tab a
+    /// color that is easy to tell apart from the others.
+    #[cfg(feature = "std")]
+    pub fn distinct_palette(n: usize, saturation: f64, lightness: f64) -> Vec<SrgbaTuple> {
+        (0..n)
+            .map(|i| Self::from_hsla(360. * i as f64 / n as f64, saturation, lightness, 1.))
+            .collect()
+    }
+
+    /// Like `distinct_palette`, but spaced evenly around the RYB
+    /// color wheel, which has fewer greens and more oranges
+    #[cfg(feature = "std")]
+    pub fn distinct_palette_ryb(n: usize, saturation: f64, lightness: f64) -> Vec<SrgbaTuple> {
+        (0..n)
+            .map(|i| {
+                let h = ryb_huge_to_rgb_hue(360. * i as f64 / n as f64);
+                Self::from_hsla(h, saturation, lightness, 1.)
+            })
+            .collect()
+    }
+
     /// Rotate the hue angle by the specified number of degrees, using
     /// the RYB color wheel
     #[cfg(feature = "std")]
@@ -642,9 +914,16 @@ impl SrgbaTuple {
 
     #[cfg(feature = "std")]
     pub fn delta_e(&self, other: &Self) -> f32 {
+        self.delta_e_method(other, DeltaEMethod::Ciede2000)
+    }
+
+    /// Like `delta_e`, but using the specified formula; `Cie76`
+    /// is much cheaper when comparing against many candidates
+    #[cfg(feature = "std")]
+    pub fn delta_e_method(&self, other: &Self, method: DeltaEMethod) -> f32 {
         let a = self.lab_value();
         let b = other.lab_value();
-        *deltae::DeltaE::new(a, b, deltae::DEMethod::DE2000).value()
+        *deltae::DeltaE::new(a, b, method.into()).value()
     }
 
     #[cfg(feature = "std")]
@@ -665,6 +944,44 @@ impl SrgbaTuple {
             .ensure_contrast_ratio(&other.to_linear(), min_ratio)
             .map(|linear| linear.to_srgb())
     }
+
+    /// Returns whichever of opaque black or white has the greater
+    /// contrast against the background color `bg`.
+    /// See `LinearRgba::max_contrast_bw`.
+    #[cfg(feature = "std")]
+    pub fn max_contrast_bw(bg: &Self) -> Self {
+        if bg.to_linear().prefers_black_text() {
+            Self(0., 0., 0., 1.)
+        } else {
+            Self(1., 1., 1., 1.)
+        }
+    }
+
+    /// Treating `self` as a background color, returns whichever of
+    /// `candidates` has the highest contrast ratio against it, making
+    /// it the most readable text color.  If `candidates` is empty,
+    /// chooses between black and white.
+    #[cfg(feature = "std")]
+    pub fn best_text_color(&self, candidates: &[SrgbaTuple]) -> SrgbaTuple {
+        const BLACK_AND_WHITE: [SrgbaTuple; 2] =
+            [SrgbaTuple(0., 0., 0., 1.), SrgbaTuple(1., 1., 1., 1.)];
+        let candidates = if candidates.is_empty() {
+            &BLACK_AND_WHITE
+        } else {
+            candidates
+        };
+        let bg = self.to_linear();
+        let mut best = candidates[0];
+        let mut best_ratio = best.to_linear().contrast_ratio(&bg);
+        for &c in &candidates[1..] {
+            let ratio = c.to_linear().contrast_ratio(&bg);
+            if ratio > best_ratio {
+                best = c;
+                best_ratio = ratio;
+            }
+        }
+        best
+    }
 }
 
 /// Convert an RGB color space hue angle to an RYB colorspace hue angle
@@ -728,12 +1045,12 @@ fn normalize_angle(t: f64) -> f64 {
 fn apply_scale(current: f64, factor: f64) -> f64 {
     let difference = if factor >= 0. { 1.0 - current } else { current };
     let delta = difference.max(0.) * factor;
-    (current + delta).max(0.)
+    (current + delta).clamp(0., 1.)
 }
 
 #[cfg(feature = "std")]
 fn apply_fixed(current: f64, amount: f64) -> f64 {
-    (current + amount).max(0.)
+    (current + amount).clamp(0., 1.)
 }
 
 impl Hash for SrgbaTuple {
@@ -747,6 +1064,31 @@ impl Hash for SrgbaTuple {
 
 impl Eq for SrgbaTuple {}
 
+/// Parses `RGB`, `RRGGBB` or `RRGGBBAA` hex digits with CSS
+/// semantics, for colors written without the leading `#`.
+/// Any other number of digits is rejected, as eg: `beef` is as
+/// likely to be a typo as an `RGBA` color.
+fn parse_bare_hex(hex: &str) -> Result<SrgbaTuple, ()> {
+    let nybble = |i: usize| -> Result<u8, ()> {
+        match (hex.as_bytes()[i] as char).to_digit(16) {
+            Some(v) => Ok(v as u8),
+            None => Err(()),
+        }
+    };
+    let byte = |i: usize| -> Result<f32, ()> {
+        Ok(((nybble(2 * i)? << 4) | nybble(2 * i + 1)?) as f32 / 255.)
+    };
+    match hex.len() {
+        3 => {
+            let short = |i: usize| -> Result<f32, ()> { Ok((nybble(i)? * 17) as f32 / 255.) };
+            Ok(SrgbaTuple(short(0)?, short(1)?, short(2)?, 1.0))
+        }
+        6 => Ok(SrgbaTuple(byte(0)?, byte(1)?, byte(2)?, 1.0)),
+        8 => Ok(SrgbaTuple(byte(0)?, byte(1)?, byte(2)?, byte(3)?)),
+        _ => Err(()),
+    }
+}
+
 fn x_parse_color_component(value: &str) -> Result<f32, ()> {
     let mut component = 0u16;
     let mut num_digits = 0;
@@ -773,6 +1115,108 @@ fn x_parse_color_component(value: &str) -> Result<f32, ()> {
     }) / 255.0)
 }
 
+/// Parses a CSS number, or a percentage where 100% corresponds
+/// to `percent_scale`.
+#[cfg(not(feature = "std"))]
+fn css_number(s: &str, percent_scale: f32) -> Option<f32> {
+    match s.strip_suffix('%') {
+        Some(pct) => pct.parse::<f32>().ok().map(|v| v / 100. * percent_scale),
+        None => s.parse().ok(),
+    }
+}
+
+/// Parses the CSS `rgb()` and `rgba()` functions with 0-255 or
+/// percentage channels, in either the legacy comma separated or the
+/// space separated syntax.  With the `std` feature csscolorparser
+/// takes care of these.
+#[cfg(not(feature = "std"))]
+fn parse_css_rgb_function(s: &str) -> Option<SrgbaTuple> {
+    let (name, args) = s.strip_suffix(')')?.split_once('(')?;
+    if !matches!(name.trim(), "rgb" | "rgba") {
+        return None;
+    }
+    let (args, slash_alpha) = match args.split_once('/') {
+        Some((args, alpha)) => (args, Some(alpha.trim())),
+        None => (args, None),
+    };
+
+    let fields: Vec<&str> = if args.contains(',') {
+        args.split(',').map(str::trim).collect()
+    } else {
+        args.split_ascii_whitespace().collect()
+    };
+    let (r, g, b, alpha) = match (fields.as_slice(), slash_alpha) {
+        (&[r, g, b], alpha) => (r, g, b, alpha),
+        (&[r, g, b, a], None) => (r, g, b, Some(a)),
+        _ => return None,
+    };
+
+    let channel = |v: &str| {
+        css_number(v, 255.)
+            .filter(|v| (0. ..=255.).contains(v))
+            .map(|v| v / 255.)
+    };
+    let alpha = match alpha {
+        Some(a) => css_number(a, 1.0)?.clamp(0., 1.),
+        None => 1.0,
+    };
+    Some(SrgbaTuple(channel(r)?, channel(g)?, channel(b)?, alpha))
+}
+
+/// Parses the CSS Color 4 `oklab()`, `oklch()` and `color(srgb ...)`
+/// functions.  With the `std` feature csscolorparser takes care of
+/// these; this provides the same support for `no_std` builds.
+#[cfg(not(feature = "std"))]
+fn parse_css4_color_function(s: &str) -> Option<SrgbaTuple> {
+    let (name, args) = s.strip_suffix(')')?.split_once('(')?;
+    let (args, alpha) = match args.split_once('/') {
+        Some((args, alpha)) => (args, css_number(alpha.trim(), 1.0)?),
+        None => (args, 1.0),
+    };
+
+    let mut fields = args.split_ascii_whitespace();
+    if name.trim() == "color" && fields.next()? != "srgb" {
+        return None;
+    }
+    let (x, y, z) = (fields.next()?, fields.next()?, fields.next()?);
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let linear = match name.trim() {
+        "oklab" => LinearRgba::from_oklaba(
+            css_number(x, 1.0)?,
+            css_number(y, 0.4)?,
+            css_number(z, 0.4)?,
+            alpha,
+        ),
+        "oklch" => {
+            let chroma = css_number(y, 0.4)?;
+            let hue = z.strip_suffix("deg").unwrap_or(z).parse::<f32>().ok()?;
+            let hue = hue.to_radians();
+            LinearRgba::from_oklaba(
+                css_number(x, 1.0)?,
+                chroma * hue.cos(),
+                chroma * hue.sin(),
+                alpha,
+            )
+        }
+        "color" => {
+            return Some(SrgbaTuple(
+                css_number(x, 1.0)?,
+                css_number(y, 1.0)?,
+                css_number(z, 1.0)?,
+                alpha,
+            ));
+        }
+        _ => return None,
+    };
+
+    // Colors outside of the sRGB gamut are clipped to it
+    let LinearRgba(r, g, b, a) = linear;
+    Some(LinearRgba(r.clamp(0., 1.), g.clamp(0., 1.), b.clamp(0., 1.), a).to_srgb())
+}
+
 impl FromStr for SrgbaTuple {
     type Err = ();
 
@@ -896,6 +1340,12 @@ impl FromStr for SrgbaTuple {
             } else {
                 Err(())
             }
+        } else if !s.is_empty()
+            && s.bytes().all(|b| b.is_ascii_hexdigit())
+            && Self::from_named(s).is_none()
+        {
+            // Probably `RRGGBB`, as written by some OSC responses
+            parse_bare_hex(s)
         } else {
             #[cfg(feature = "std")]
             {
@@ -903,11 +1353,106 @@ impl FromStr for SrgbaTuple {
                     return Ok(Self(c.r as f32, c.g as f32, c.b as f32, c.a as f32));
                 }
             }
+            #[cfg(not(feature = "std"))]
+            {
+                if let Some(c) = parse_css_rgb_function(s).or_else(|| parse_css4_color_function(s))
+                {
+                    return Ok(c);
+                }
+            }
             Self::from_named(s).ok_or(())
         }
     }
 }
 
+/// The 16 ANSI colors together with the default foreground
+/// and background colors.
+#[derive(Copy, Clone, Debug, PartialEq, FromDynamic, ToDynamic)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct AnsiPalette {
+    pub colors: [SrgbaTuple; 16],
+    pub foreground: SrgbaTuple,
+    pub background: SrgbaTuple,
+}
+
+const fn srgb8(red: u8, green: u8, blue: u8) -> SrgbaTuple {
+    SrgbaTuple(
+        red as f32 / 255.,
+        green as f32 / 255.,
+        blue as f32 / 255.,
+        1.0,
+    )
+}
+
+impl AnsiPalette {
+    /// The palette used when nothing else has been configured
+    pub const DEFAULT: Self = Self {
+        colors: [
+            // Black
+            srgb8(0x00, 0x00, 0x00),
+            // Maroon
+            srgb8(0xcc, 0x55, 0x55),
+            // Green
+            srgb8(0x55, 0xcc, 0x55),
+            // Olive
+            srgb8(0xcd, 0xcd, 0x55),
+            // Navy
+            srgb8(0x54, 0x55, 0xcb),
+            // Purple
+            srgb8(0xcc, 0x55, 0xcc),
+            // Teal
+            srgb8(0x7a, 0xca, 0xca),
+            // Silver
+            srgb8(0xcc, 0xcc, 0xcc),
+            // Grey
+            srgb8(0x55, 0x55, 0x55),
+            // Red
+            srgb8(0xff, 0x55, 0x55),
+            // Lime
+            srgb8(0x55, 0xff, 0x55),
+            // Yellow
+            srgb8(0xff, 0xff, 0x55),
+            // Blue
+            srgb8(0x55, 0x55, 0xff),
+            // Fuchsia
+            srgb8(0xff, 0x55, 0xff),
+            // Aqua
+            srgb8(0x55, 0xff, 0xff),
+            // White
+            srgb8(0xff, 0xff, 0xff),
+        ],
+        // Grey70
+        foreground: srgb8(0xb2, 0xb2, 0xb2),
+        background: srgb8(0x00, 0x00, 0x00),
+    };
+
+    /// Build a palette from the `base00` through `base0F` colors of a
+    /// base16 scheme, using the same mapping as base16-shell.
+    pub fn from_base16(base: [SrgbaTuple; 16]) -> Self {
+        Self {
+            colors: [
+                base[0x0], base[0x8], base[0xB], base[0xA], base[0xD], base[0xE], base[0xC],
+                base[0x5], base[0x3], base[0x8], base[0xB], base[0xA], base[0xD], base[0xE],
+                base[0xC], base[0x7],
+            ],
+            foreground: base[0x5],
+            background: base[0x0],
+        }
+    }
+
+    /// Returns the ANSI color at `idx`, or `None` if `idx` is not
+    /// in the range 0-15.
+    pub fn color(&self, idx: u8) -> Option<SrgbaTuple> {
+        self.colors.get(idx as usize).copied()
+    }
+}
+
+impl Default for AnsiPalette {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// A pixel value encoded as linear RGBA values in f32 format (range: 0.0-1.0)
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
 pub struct LinearRgba(pub f32, pub f32, pub f32, pub f32);
@@ -993,6 +1538,80 @@ impl LinearRgba {
         Self(self.0, self.1, self.2, self.3 * alpha)
     }
 
+    /// Returns self with the alpha channel replaced by `alpha`,
+    /// clamped to 0.0-1.0, unlike `mul_alpha` which scales it.
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self(self.0, self.1, self.2, alpha.clamp(0., 1.))
+    }
+
+    /// Returns self with each channel clamped to 0.0-1.0, as color
+    /// math can push channels out of range, which would otherwise
+    /// wrap or truncate badly when converted to u8.
+    pub fn clamp(self) -> Self {
+        Self(
+            self.0.clamp(0., 1.),
+            self.1.clamp(0., 1.),
+            self.2.clamp(0., 1.),
+            self.3.clamp(0., 1.),
+        )
+    }
+
+    /// Returns true if every channel is within 0.0-1.0
+    pub fn is_in_gamut(&self) -> bool {
+        [self.0, self.1, self.2, self.3]
+            .iter()
+            .all(|c| (0. ..=1.).contains(c))
+    }
+
+    /// Composites self on top of `under` using the Porter-Duff
+    /// "over" operator.  The blend is computed in premultiplied
+    /// linear space and the result is returned with straight alpha.
+    pub fn over(&self, under: &Self) -> Self {
+        if self.3 >= 1.0 {
+            return *self;
+        }
+        if self.is_fully_transparent() {
+            return *under;
+        }
+
+        let under_weight = under.3 * (1.0 - self.3);
+        let alpha = self.3 + under_weight;
+        if alpha <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+
+        let blend = |src: f32, dst: f32| (src * self.3 + dst * under_weight) / alpha;
+        Self(
+            blend(self.0, under.0),
+            blend(self.1, under.1),
+            blend(self.2, under.2),
+            alpha,
+        )
+    }
+
+    /// Returns the mean of `colors`.  The channels are weighted by
+    /// alpha, so that transparent pixels don't wash out the result,
+    /// and the result is returned with straight alpha.
+    /// An empty slice yields `TRANSPARENT`.
+    pub fn average(colors: &[Self]) -> Self {
+        if colors.is_empty() {
+            return Self::TRANSPARENT;
+        }
+
+        let (mut r, mut g, mut b, mut a) = (0., 0., 0., 0.);
+        for c in colors {
+            r += c.0 * c.3;
+            g += c.1 * c.3;
+            b += c.2 * c.3;
+            a += c.3;
+        }
+        if a <= 0.0 {
+            return Self::TRANSPARENT;
+        }
+
+        Self(r / a, g / a, b / a, a / colors.len() as f32)
+    }
+
     /// Convert to an SRGB u32 pixel
     pub fn srgba_pixel(self) -> SrgbaPixel {
         SrgbaPixel::rgba(
@@ -1018,6 +1637,23 @@ impl LinearRgba {
         )
     }
 
+    /// Treating self as premultiplied, as produced by
+    /// `SrgbaTuple::to_linear_premultiplied`, converts back to
+    /// srgb with straight alpha in a single step.
+    /// Fully transparent colors yield transparent black.
+    pub fn to_srgb_straight(&self) -> SrgbaTuple {
+        let a = self.3;
+        if a == 0. {
+            return SrgbaTuple(0., 0., 0., 0.);
+        }
+        SrgbaTuple(
+            linear_to_srgb_f32(self.0 / a),
+            linear_to_srgb_f32(self.1 / a),
+            linear_to_srgb_f32(self.2 / a),
+            a,
+        )
+    }
+
     #[cfg(feature = "std")]
     pub fn relative_luminance(&self) -> f32 {
         0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
@@ -1053,7 +1689,6 @@ impl LinearRgba {
         [l, a, b, alpha]
     }
 
-    #[cfg(feature = "std")]
     fn from_oklaba(l: f32, a: f32, b: f32, alpha: f32) -> Self {
         let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
         let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
@@ -1124,6 +1759,26 @@ impl LinearRgba {
         // What they had was as good as it gets
         None
     }
+
+    /// Returns whichever of opaque black or white has the greater
+    /// contrast against the background color `bg`.  Unlike
+    /// `ensure_contrast_ratio` this makes no attempt to preserve the
+    /// hue of the foreground, which gives the most legible result.
+    #[cfg(feature = "std")]
+    pub fn max_contrast_bw(bg: &Self) -> Self {
+        if bg.prefers_black_text() {
+            Self(0., 0., 0., 1.)
+        } else {
+            Self(1., 1., 1., 1.)
+        }
+    }
+
+    /// Black and white have equal contrast against a luminance of
+    /// about 0.179, where (L + 0.05) / 0.05 == 1.05 / (L + 0.05)
+    #[cfg(feature = "std")]
+    fn prefers_black_text(&self) -> bool {
+        self.relative_luminance() > 0.179
+    }
 }
 
 #[cfg(test)]
@@ -1272,6 +1927,31 @@ mod tests {
         assert!((a - 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn srgba_pixel_rgba_bytes_order() {
+        let p = SrgbaPixel::rgba(0x11, 0x22, 0x33, 0x44);
+        assert_eq!(p.to_rgba_bytes(), [0x11, 0x22, 0x33, 0x44]);
+        assert_eq!(SrgbaPixel::from_rgba_bytes([0x11, 0x22, 0x33, 0x44]), p);
+        assert_eq!(
+            SrgbaPixel::from_rgba_bytes([255, 128, 0, 200]).as_rgba(),
+            (255, 128, 0, 200)
+        );
+    }
+
+    #[test]
+    fn srgba_pixel_rgba_bytes_roundtrip() {
+        for bytes in [
+            [0, 0, 0, 0],
+            [1, 2, 3, 4],
+            [255, 0, 255, 128],
+            [9, 8, 7, 255],
+        ] {
+            assert_eq!(SrgbaPixel::from_rgba_bytes(bytes).to_rgba_bytes(), bytes);
+        }
+        let p = SrgbaPixel::rgba(100, 150, 200, 255);
+        assert_eq!(SrgbaPixel::from_rgba_bytes(p.to_rgba_bytes()), p);
+    }
+
     #[test]
     fn srgba_pixel_debug_eq() {
         let a = SrgbaPixel::rgba(1, 2, 3, 4);
@@ -1414,36 +2094,178 @@ mod tests {
         assert_eq!(t.to_x11_16bit_rgb_string(), "rgb:0000/0000/0000");
     }
 
-    // ── SrgbaTuple named colors ───────────────────────────────
+    #[test]
+    fn to_css_string_opaque() {
+        let t = SrgbaTuple(1.0, 0.5, 0.0, 1.0);
+        assert_eq!(t.to_css_string(), "rgb(255 128 0)");
+    }
 
     #[test]
-    fn named_transparent() {
-        let t = SrgbaTuple::from_named("transparent").unwrap();
-        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    fn to_css_string_transparent() {
+        let t = SrgbaTuple(0.0, 0.0, 1.0, 0.5);
+        assert_eq!(t.to_css_string(), "rgb(0 0 255 / 0.5)");
     }
 
+    #[cfg(feature = "std")]
     #[test]
-    fn named_none() {
-        let t = SrgbaTuple::from_named("none").unwrap();
-        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    fn to_css_string_roundtrips_through_csscolorparser() {
+        for color in [
+            SrgbaTuple::from_str("#ff8000").unwrap(),
+            SrgbaTuple::from_str("#123456").unwrap(),
+            SrgbaTuple(0.2, 0.4, 0.6, 0.25),
+        ] {
+            let css = color.to_css_string();
+            let parsed = csscolorparser::parse(&css).unwrap();
+            assert_eq!(
+                (parsed.r * 255.).round() as u8,
+                (color.0 * 255.).round() as u8,
+                "{}",
+                css
+            );
+            assert_eq!(
+                (parsed.g * 255.).round() as u8,
+                (color.1 * 255.).round() as u8,
+                "{}",
+                css
+            );
+            assert_eq!(
+                (parsed.b * 255.).round() as u8,
+                (color.2 * 255.).round() as u8,
+                "{}",
+                css
+            );
+            assert!((parsed.a as f32 - color.3).abs() < 0.001, "{}", css);
+        }
     }
 
     #[test]
-    fn named_clear() {
-        let t = SrgbaTuple::from_named("clear").unwrap();
-        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    fn to_rgb_int_string_red() {
+        let t = SrgbaTuple(1.0, 0.0, 0.0, 0.5);
+        assert_eq!(t.to_rgb_int_string(), "rgb(255,0,0)");
     }
 
     #[test]
-    fn named_case_insensitive() {
-        let a = SrgbaTuple::from_named("Red").unwrap();
-        let b = SrgbaTuple::from_named("red").unwrap();
-        assert_eq!(a, b);
+    fn rgb_int_string_roundtrips_comma_separated() {
+        let c = SrgbaTuple::from_str("rgb(255,0,0)").unwrap();
+        assert_eq!(c.as_rgba_u8(), (255, 0, 0, 255));
+        assert_eq!(c.to_rgb_int_string(), "rgb(255,0,0)");
     }
 
     #[test]
-    fn named_unknown_returns_none() {
-        assert!(SrgbaTuple::from_named("notacolor").is_none());
+    fn rgb_int_string_roundtrips_space_separated() {
+        let c = SrgbaTuple::from_str("rgb(0 128 255)").unwrap();
+        assert_eq!(c.as_rgba_u8(), (0, 128, 255, 255));
+        assert_eq!(c.to_rgb_int_string(), "rgb(0,128,255)");
+        assert_eq!(SrgbaTuple::from_str(&c.to_rgb_int_string()).unwrap(), c);
+    }
+
+    #[test]
+    fn rgb_function_accepts_alpha() {
+        let c = SrgbaTuple::from_str("rgba(0, 0, 255, 0.5)").unwrap();
+        assert_eq!(c, SrgbaTuple(0.0, 0.0, 1.0, 0.5));
+        let c = SrgbaTuple::from_str("rgb(0 0 255 / 50%)").unwrap();
+        assert_eq!(c, SrgbaTuple(0.0, 0.0, 1.0, 0.5));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_rejects_malformed_rgb_functions() {
+        assert!(SrgbaTuple::from_str("rgb(255,0)").is_err());
+        assert!(SrgbaTuple::from_str("rgb(256 0 0)").is_err());
+        assert!(SrgbaTuple::from_str("rgb(a,b,c)").is_err());
+        assert!(SrgbaTuple::from_str("rgb(1 2 3 4 5)").is_err());
+    }
+
+    // ── no_std CSS4 color functions ───────────────────────────
+
+    #[cfg(not(feature = "std"))]
+    fn assert_srgb_near(c: SrgbaTuple, expected: (f32, f32, f32)) {
+        assert!(
+            (c.0 - expected.0).abs() < 0.01
+                && (c.1 - expected.1).abs() < 0.01
+                && (c.2 - expected.2).abs() < 0.01,
+            "{:?} is not close to {:?}",
+            c,
+            expected
+        );
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_parses_oklch_red() {
+        let c = SrgbaTuple::from_str("oklch(0.628 0.2577 29.23)").unwrap();
+        assert_srgb_near(c, (1.0, 0.0, 0.0));
+        assert_eq!(c.3, 1.0);
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_parses_oklch_blue_with_alpha() {
+        let c = SrgbaTuple::from_str("oklch(45.2% 0.313 264.05deg / 50%)").unwrap();
+        assert_srgb_near(c, (0.0, 0.0, 1.0));
+        assert!((c.3 - 0.5).abs() < 0.001);
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_parses_oklab() {
+        assert_srgb_near(
+            SrgbaTuple::from_str("oklab(1 0 0)").unwrap(),
+            (1.0, 1.0, 1.0),
+        );
+        assert_srgb_near(
+            SrgbaTuple::from_str("oklab(0.8664 -0.2339 0.1795)").unwrap(),
+            (0.0, 1.0, 0.0),
+        );
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_parses_color_srgb() {
+        let c = SrgbaTuple::from_str("color(srgb 1 50% 0 / 0.25)").unwrap();
+        assert_eq!(c, SrgbaTuple(1.0, 0.5, 0.0, 0.25));
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_rejects_malformed_css4_functions() {
+        assert!(SrgbaTuple::from_str("oklch(0.5 0.1)").is_err());
+        assert!(SrgbaTuple::from_str("oklab(0.5 0 0 0)").is_err());
+        assert!(SrgbaTuple::from_str("oklab(a b c)").is_err());
+        assert!(SrgbaTuple::from_str("color(display-p3 1 0 0)").is_err());
+        assert!(SrgbaTuple::from_str("oklab(0.5 0 0").is_err());
+    }
+
+    // ── SrgbaTuple named colors ───────────────────────────────
+
+    #[test]
+    fn named_transparent() {
+        let t = SrgbaTuple::from_named("transparent").unwrap();
+        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn named_none() {
+        let t = SrgbaTuple::from_named("none").unwrap();
+        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn named_clear() {
+        let t = SrgbaTuple::from_named("clear").unwrap();
+        assert_eq!(t, SrgbaTuple(0.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn named_case_insensitive() {
+        let a = SrgbaTuple::from_named("Red").unwrap();
+        let b = SrgbaTuple::from_named("red").unwrap();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn named_unknown_returns_none() {
+        assert!(SrgbaTuple::from_named("notacolor").is_none());
     }
 
     // ── SrgbaTuple to/from linear ─────────────────────────────
@@ -1511,6 +2333,84 @@ mod tests {
         let _ = (s1, s2, s3); // just verify it doesn't panic
     }
 
+    #[cfg(feature = "std")]
+    fn assert_valid_color(c: SrgbaTuple) {
+        for v in [c.0, c.1, c.2, c.3] {
+            assert!((0.0..=1.0).contains(&v), "{:?}", c);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn triad_ryb_differs_from_rgb_triad() {
+        let c = SrgbaTuple::from_str("hsl:0 100 50").unwrap();
+        let (r1, r2) = c.triad_ryb();
+        let (t1, t2) = c.triad();
+        assert_valid_color(r1);
+        assert_valid_color(r2);
+        assert!((r1.to_hsla().0 - t1.to_hsla().0).abs() > 1.0);
+        assert!((r2.to_hsla().0 - t2.to_hsla().0).abs() > 1.0);
+
+        // Red's RYB triad includes yellow, where the RGB one has green
+        assert!((r1.to_hsla().0 - 60.0).abs() < 2.0, "{:?}", r1.to_hsla());
+        let blueish = r2.to_hsla().0;
+        assert!((180.0..=250.0).contains(&blueish), "{}", blueish);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn tetrad_ryb_returns_valid_colors() {
+        for color in ["hsl:0 100 50", "hsl:200 60 40", "#336699"] {
+            let c = SrgbaTuple::from_str(color).unwrap();
+            let (a, b, comp) = c.tetrad_ryb();
+            for t in [a, b, comp] {
+                assert_valid_color(t);
+                assert_eq!(t.3, c.3);
+            }
+            assert_eq!(comp, c.complement_ryb());
+        }
+    }
+
+    // ── distinct_palette ─────────────────────────────────────
+
+    #[cfg(feature = "std")]
+    fn hue_distance(a: f64, b: f64) -> f64 {
+        let d = (a - b).rem_euclid(360.);
+        d.min(360. - d)
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn distinct_palette_of_three_is_120_degrees_apart() {
+        let palette = SrgbaTuple::distinct_palette(3, 0.8, 0.5);
+        assert_eq!(palette.len(), 3);
+        let hues: Vec<f64> = palette.iter().map(|c| c.to_hsla().0).collect();
+        for i in 0..3 {
+            let d = hue_distance(hues[i], hues[(i + 1) % 3]);
+            assert!((d - 120.).abs() < 1., "{:?}", hues);
+        }
+        for c in &palette {
+            assert_valid_color(*c);
+            assert_eq!(c.3, 1.);
+        }
+        assert!(SrgbaTuple::distinct_palette(0, 0.8, 0.5).is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn distinct_palette_colors_are_perceptually_distinct() {
+        for palette in [
+            SrgbaTuple::distinct_palette(6, 0.7, 0.5),
+            SrgbaTuple::distinct_palette_ryb(6, 0.7, 0.5),
+        ] {
+            for (i, a) in palette.iter().enumerate() {
+                for b in &palette[i + 1..] {
+                    assert!(a.delta_e(b) > 10., "{:?} {:?}", a, b);
+                }
+            }
+        }
+    }
+
     // ── LinearRgba ────────────────────────────────────────────
 
     #[test]
@@ -1651,6 +2551,61 @@ mod tests {
         assert!(SrgbaTuple::from_str("#12345").is_err());
     }
 
+    #[test]
+    fn from_str_bare_hex() {
+        let red = SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        assert_eq!(SrgbaTuple::from_str("ff0000").unwrap(), red);
+        assert_eq!(SrgbaTuple::from_str("FF0000").unwrap(), red);
+        assert_eq!(SrgbaTuple::from_str("f00").unwrap(), red);
+
+        let t = SrgbaTuple::from_str("00ff0080").unwrap();
+        assert_eq!((t.0, t.1, t.2), (0.0, 1.0, 0.0));
+        assert!((t.3 - 0.502).abs() < 0.01);
+    }
+
+    #[test]
+    fn from_str_bare_hex_rejects_ambiguous_lengths() {
+        assert!(SrgbaTuple::from_str("beef").is_err());
+        assert!(SrgbaTuple::from_str("12345").is_err());
+        assert!(SrgbaTuple::from_str("0123456789ab").is_err());
+    }
+
+    #[test]
+    fn from_str_bare_hex_leaves_named_colors_alone() {
+        assert_eq!(
+            SrgbaTuple::from_str("red").unwrap(),
+            SrgbaTuple::from_named("red").unwrap()
+        );
+        assert_eq!(
+            SrgbaTuple::from_str("beige").unwrap(),
+            SrgbaTuple::from_named("beige").unwrap()
+        );
+    }
+
+    // ── from_argb_hex ─────────────────────────────────────────
+
+    #[test]
+    fn from_argb_hex_alpha_first() {
+        let t = SrgbaTuple::from_argb_hex("#80ff0000").unwrap();
+        assert_eq!(t.0, 1.0);
+        assert_eq!(t.1, 0.0);
+        assert_eq!(t.2, 0.0);
+        assert!((t.3 - 0.502).abs() < 0.01);
+
+        let t = SrgbaTuple::from_argb_hex("FF00FF00").unwrap();
+        assert_eq!(t, SrgbaTuple(0., 1., 0., 1.));
+    }
+
+    #[test]
+    fn from_argb_hex_rejects_other_lengths() {
+        assert!(SrgbaTuple::from_argb_hex("#ff0000").is_err());
+        assert!(SrgbaTuple::from_argb_hex("#f00").is_err());
+        assert!(SrgbaTuple::from_argb_hex("#80ff00000").is_err());
+        assert!(SrgbaTuple::from_argb_hex("#+0ff0000").is_err());
+        assert!(SrgbaTuple::from_argb_hex("#80ff00zz").is_err());
+        assert!(SrgbaTuple::from_argb_hex("").is_err());
+    }
+
     // ── delta_e ───────────────────────────────────────────────
 
     #[cfg(feature = "std")]
@@ -1670,6 +2625,32 @@ mod tests {
         assert!(de > 1.0);
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn delta_e_method_identical_colors_is_zero() {
+        let c = SrgbaTuple(0.2, 0.6, 0.4, 1.0);
+        for method in [
+            DeltaEMethod::Cie76,
+            DeltaEMethod::Cie94,
+            DeltaEMethod::Ciede2000,
+        ] {
+            assert!(c.delta_e_method(&c, method) < 0.01, "{:?}", method);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn delta_e_method_cie76_differs_from_de2000() {
+        let a = SrgbaTuple(1.0, 0.0, 0.0, 1.0);
+        let b = SrgbaTuple(0.0, 0.0, 1.0, 1.0);
+        let cie76 = a.delta_e_method(&b, DeltaEMethod::Cie76);
+        let de2000 = a.delta_e_method(&b, DeltaEMethod::Ciede2000);
+        assert!(cie76 > 1.0);
+        assert!(de2000 > 1.0);
+        assert!((cie76 - de2000).abs() > 1.0);
+        assert_eq!(de2000, a.delta_e(&b));
+    }
+
     // ── SrgbaTuple::to_srgb_u8 ──────────────────────────────
 
     #[test]
@@ -1770,6 +2751,43 @@ mod tests {
         assert!(l_light > l_orig);
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn fixed_adjustments_clamp_to_valid_hsl() {
+        let c = SrgbaTuple::from_str("hsl:200 60 40").unwrap();
+        let white = c.lighten_fixed(100.0);
+        assert_valid_color(white);
+        for v in [white.0, white.1, white.2] {
+            assert!(v > 0.99, "{:?}", white);
+        }
+        let (_, _, l, _) = white.to_hsla();
+        assert!(l <= 1.0);
+
+        let black = c.lighten_fixed(-100.0);
+        assert_valid_color(black);
+        assert!(black.0 < 0.01 && black.1 < 0.01 && black.2 < 0.01);
+
+        let (_, s, _, _) = c.saturate_fixed(5.0).to_hsla();
+        assert!(s <= 1.0 + 1e-6, "{}", s);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn clamped_adjustments_match_full_scale() {
+        let c = SrgbaTuple::from_str("hsl:30 50 50").unwrap();
+        assert_eq!(c.lighten_clamped(3.0), c.lighten(1.0));
+        assert_eq!(c.lighten_clamped(-3.0), c.lighten(-1.0));
+        assert_eq!(c.saturate_clamped(2.0), c.saturate(1.0));
+        assert_eq!(c.lighten_clamped(0.25), c.lighten(0.25));
+        for t in [
+            c.lighten_clamped(10.0),
+            c.saturate_clamped(10.0),
+            c.saturate_clamped(-10.0),
+        ] {
+            assert_valid_color(t);
+        }
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn adjust_hue_fixed_rotates() {
@@ -1811,6 +2829,32 @@ mod tests {
         assert!(adjusted.2 >= 0.0 && adjusted.2 <= 1.0);
     }
 
+    #[test]
+    fn invert_black_is_white() {
+        let white = SrgbaTuple(0.0, 0.0, 0.0, 1.0).invert();
+        assert_eq!(white, SrgbaTuple(1.0, 1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn invert_preserves_alpha() {
+        let inv = SrgbaTuple(0.25, 0.5, 1.0, 0.3).invert();
+        assert_eq!(inv, SrgbaTuple(0.75, 0.5, 0.0, 0.3));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn invert_lightness_dark_blue_becomes_light_blue() {
+        let dark_blue = SrgbaTuple::from_str("hsl:220 80 20").unwrap();
+        let light_blue = dark_blue.invert_lightness();
+        let (h0, s0, l0, _) = dark_blue.to_hsla();
+        let (h1, s1, l1, a1) = light_blue.to_hsla();
+        assert!((h0 - h1).abs() < 1.0, "hue {} vs {}", h0, h1);
+        assert!((s0 - s1).abs() < 0.01);
+        assert!((l1 - (1.0 - l0)).abs() < 0.01);
+        assert!(l1 > 0.75);
+        assert!((a1 - 1.0).abs() < 0.01);
+    }
+
     // ── SrgbaTuple ToDynamic / FromDynamic ───────────────────
 
     #[test]
@@ -1845,6 +2889,108 @@ mod tests {
         assert!(result.is_err());
     }
 
+    // ── AnsiPalette ─────────────────────────────────────────
+
+    fn base16() -> [SrgbaTuple; 16] {
+        let mut base = [SrgbaTuple::default(); 16];
+        for (i, entry) in base.iter_mut().enumerate() {
+            let v = (i * 16) as u8;
+            *entry = (v, 0x20, 0xff - v).into();
+        }
+        base
+    }
+
+    #[test]
+    fn ansi_palette_from_base16_maps_entries() {
+        let base = base16();
+        let palette = AnsiPalette::from_base16(base);
+        assert_eq!(palette.color(0), Some(base[0x0]));
+        assert_eq!(palette.color(1), Some(base[0x8]));
+        assert_eq!(palette.color(2), Some(base[0xB]));
+        assert_eq!(palette.color(3), Some(base[0xA]));
+        assert_eq!(palette.color(4), Some(base[0xD]));
+        assert_eq!(palette.color(5), Some(base[0xE]));
+        assert_eq!(palette.color(6), Some(base[0xC]));
+        assert_eq!(palette.color(7), Some(base[0x5]));
+        assert_eq!(palette.color(8), Some(base[0x3]));
+        assert_eq!(palette.color(9), palette.color(1));
+        assert_eq!(palette.color(14), palette.color(6));
+        assert_eq!(palette.color(15), Some(base[0x7]));
+        assert_eq!(palette.foreground, base[0x5]);
+        assert_eq!(palette.background, base[0x0]);
+    }
+
+    #[test]
+    fn ansi_palette_color_out_of_range() {
+        assert_eq!(AnsiPalette::DEFAULT.color(16), None);
+        assert_eq!(AnsiPalette::DEFAULT.color(255), None);
+    }
+
+    #[test]
+    fn ansi_palette_default_entries() {
+        let palette = AnsiPalette::default();
+        assert_eq!(palette, AnsiPalette::DEFAULT);
+        assert_eq!(palette.color(0).unwrap().to_rgb_string(), "#000000");
+        assert_eq!(palette.color(1).unwrap().to_rgb_string(), "#cc5555");
+        assert_eq!(palette.color(15).unwrap().to_rgb_string(), "#ffffff");
+        assert_eq!(palette.foreground.to_rgb_string(), "#b2b2b2");
+    }
+
+    #[test]
+    fn ansi_palette_dynamic_roundtrip() {
+        let palette = AnsiPalette::from_base16(base16());
+        let value = palette.to_dynamic();
+        let back = AnsiPalette::from_dynamic(&value, FromDynamicOptions::default()).unwrap();
+        for idx in 0..16 {
+            assert_eq!(back.color(idx), palette.color(idx), "color {}", idx);
+        }
+        assert_eq!(back.foreground, palette.foreground);
+        assert_eq!(back.background, palette.background);
+    }
+
+    // ── with_alpha ──────────────────────────────────────────
+
+    #[test]
+    fn srgba_with_alpha_replaces_alpha() {
+        let c = SrgbaTuple(0.25, 0.5, 0.75, 0.5);
+        let t = c.with_alpha(0.3);
+        assert_eq!((t.0, t.1, t.2), (0.25, 0.5, 0.75));
+        assert_eq!(t.3, 0.3);
+        assert_eq!(c.with_alpha(1.5).3, 1.0);
+        assert_eq!(c.with_alpha(-0.5).3, 0.0);
+    }
+
+    #[test]
+    fn linear_with_alpha_replaces_alpha() {
+        let c = LinearRgba(0.1, 0.2, 0.3, 0.0);
+        let t = c.with_alpha(0.8);
+        assert_eq!((t.0, t.1, t.2), (0.1, 0.2, 0.3));
+        assert_eq!(t.3, 0.8);
+        assert_eq!(c.with_alpha(2.0).3, 1.0);
+        assert_eq!(c.with_alpha(-1.0).3, 0.0);
+    }
+
+    // ── clamp / is_in_gamut ─────────────────────────────────
+
+    #[test]
+    fn srgba_clamp_out_of_gamut() {
+        let c = SrgbaTuple(1.5, 0.5, -0.25, 1.0);
+        assert!(!c.is_in_gamut());
+        let clamped = c.clamp();
+        assert_eq!(clamped, SrgbaTuple(1.0, 0.5, 0.0, 1.0));
+        assert!(clamped.is_in_gamut());
+        assert!(SrgbaTuple(0.0, 1.0, 0.5, 0.5).is_in_gamut());
+    }
+
+    #[test]
+    fn linear_clamp_out_of_gamut() {
+        let c = LinearRgba(0.2, 1.5, 0.3, 2.0);
+        assert!(!c.is_in_gamut());
+        let clamped = c.clamp();
+        assert_eq!(clamped, LinearRgba(0.2, 1.0, 0.3, 1.0));
+        assert!(clamped.is_in_gamut());
+    }
+
     // ── LinearRgba::with_rgba ───────────────────────────────
 
     #[test]
@@ -1873,6 +3019,58 @@ mod tests {
         assert!(srgba.0 < rgba.0);
     }
 
+    // ── to_linear_premultiplied / to_srgb_straight ───────────
+
+    #[test]
+    fn to_linear_premultiplied_matches_two_step() {
+        for c in [
+            SrgbaTuple(0.8, 0.4, 0.1, 0.5),
+            SrgbaTuple(0.02, 0.5, 1.0, 0.25),
+            SrgbaTuple(1.0, 1.0, 1.0, 1.0),
+        ] {
+            let linear = c.to_linear();
+            let expected = LinearRgba(
+                linear.0 * linear.3,
+                linear.1 * linear.3,
+                linear.2 * linear.3,
+                linear.3,
+            );
+            let got = c.to_linear_premultiplied();
+            assert!(
+                (got.0 - expected.0).abs() < 1e-6,
+                "{:?} {:?}",
+                got,
+                expected
+            );
+            assert!(
+                (got.1 - expected.1).abs() < 1e-6,
+                "{:?} {:?}",
+                got,
+                expected
+            );
+            assert!(
+                (got.2 - expected.2).abs() < 1e-6,
+                "{:?} {:?}",
+                got,
+                expected
+            );
+            assert_eq!(got.3, c.3);
+        }
+    }
+
+    #[test]
+    fn to_srgb_straight_roundtrips() {
+        let c = SrgbaTuple(0.8, 0.02, 0.5, 0.4);
+        let back = c.to_linear_premultiplied().to_srgb_straight();
+        assert!((back.0 - c.0).abs() < 1e-4, "{:?}", back);
+        assert!((back.1 - c.1).abs() < 1e-4, "{:?}", back);
+        assert!((back.2 - c.2).abs() < 1e-4, "{:?}", back);
+        assert_eq!(back.3, c.3);
+
+        let clear = SrgbaTuple(0.5, 0.5, 0.5, 0.0).to_linear_premultiplied();
+        assert_eq!(clear.to_srgb_straight(), SrgbaTuple(0., 0., 0., 0.));
+    }
+
     // ── LinearRgba ensure_contrast_ratio ─────────────────────
 
     #[cfg(feature = "std")]
@@ -1903,6 +3101,36 @@ mod tests {
         assert!(result.is_some());
     }
 
+    // ── max_contrast_bw ──────────────────────────────────────
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn max_contrast_bw_picks_white_on_dark_and_black_on_light() {
+        let black = LinearRgba(0.0, 0.0, 0.0, 1.0);
+        let white = LinearRgba(1.0, 1.0, 1.0, 1.0);
+        let dark = LinearRgba(0.02, 0.03, 0.1, 1.0);
+        let light = LinearRgba(0.8, 0.8, 0.6, 1.0);
+        assert_eq!(LinearRgba::max_contrast_bw(&dark), white);
+        assert_eq!(LinearRgba::max_contrast_bw(&light), black);
+        assert!(white.contrast_ratio(&dark) > black.contrast_ratio(&dark));
+        assert!(black.contrast_ratio(&light) > white.contrast_ratio(&light));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn srgba_max_contrast_bw() {
+        let navy = SrgbaTuple::from_str("navy").unwrap();
+        let yellow = SrgbaTuple::from_str("yellow").unwrap();
+        assert_eq!(
+            SrgbaTuple::max_contrast_bw(&navy),
+            SrgbaTuple(1., 1., 1., 1.)
+        );
+        assert_eq!(
+            SrgbaTuple::max_contrast_bw(&yellow),
+            SrgbaTuple(0., 0., 0., 1.)
+        );
+    }
+
     // ── LinearRgba oklaba roundtrip ──────────────────────────
 
     #[cfg(feature = "std")]
@@ -2143,6 +3371,30 @@ mod tests {
         assert!(t.1 < 0.01);
     }
 
+    // ── mean_luminance / luminance_histogram ────────────────
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn mean_luminance_of_white_and_black() {
+        let white = vec![SrgbaPixel::rgba(255, 255, 255, 255); 64];
+        assert!((mean_luminance(&white) - 1.0).abs() < 0.001);
+        let black = vec![SrgbaPixel::rgba(0, 0, 0, 255); 64];
+        assert!(mean_luminance(&black).abs() < 0.001);
+        assert_eq!(mean_luminance(&[]), 0.);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn luminance_histogram_of_half_black_half_white() {
+        let mut pixels = vec![SrgbaPixel::rgba(0, 0, 0, 255); 10];
+        pixels.extend(vec![SrgbaPixel::rgba(255, 255, 255, 255); 10]);
+        let histogram = luminance_histogram(&pixels);
+        assert_eq!(histogram[0], 10);
+        assert_eq!(histogram[15], 10);
+        assert_eq!(histogram.iter().sum::<usize>(), 20);
+        assert!((mean_luminance(&pixels) - 0.5).abs() < 0.001);
+    }
+
     // ── Conversion table functions ──────────────────────────
 
     #[cfg(feature = "std")]
@@ -2172,6 +3424,25 @@ mod tests {
         }
     }
 
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn no_std_srgb8_to_linear_table_matches_formula() {
+        let table = srgb8_to_linear_table();
+        for val in 0..=255u8 {
+            let expected = srgb_f32_to_linear(val as f32 / 255.0);
+            assert!(
+                (table[val as usize] - expected).abs() < 1e-6,
+                "input {}: table {} formula {}",
+                val,
+                table[val as usize],
+                expected
+            );
+            assert_eq!(srgb8_to_linear_f32(val), table[val as usize]);
+        }
+        assert_eq!(table[0], 0.0);
+        assert_eq!(table[255], 1.0);
+    }
+
     // ── SrgbaTuple into (f32,f32,f32,f32) ───────────────────
 
     #[test]
@@ -2206,6 +3477,85 @@ mod tests {
         assert!(!c.is_fully_transparent());
     }
 
+    // ── LinearRgba::over ────────────────────────────────────
+
+    #[test]
+    fn over_transparent_source_returns_under() {
+        let under = LinearRgba(0.2, 0.4, 0.6, 0.8);
+        let src = LinearRgba(1.0, 1.0, 1.0, 0.0);
+        assert_eq!(src.over(&under), under);
+    }
+
+    #[test]
+    fn over_opaque_source_returns_self() {
+        let under = LinearRgba(0.2, 0.4, 0.6, 0.8);
+        let src = LinearRgba(0.9, 0.1, 0.3, 1.0);
+        assert_eq!(src.over(&under), src);
+    }
+
+    #[test]
+    fn over_half_red_on_solid_blue() {
+        let red = LinearRgba(1.0, 0.0, 0.0, 0.5);
+        let blue = LinearRgba(0.0, 0.0, 1.0, 1.0);
+        let LinearRgba(r, g, b, a) = red.over(&blue);
+        assert!((r - 0.5).abs() < 1e-6);
+        assert!(g.abs() < 1e-6);
+        assert!((b - 0.5).abs() < 1e-6);
+        assert!((a - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn over_translucent_layers_unpremultiplies_result() {
+        let red = LinearRgba(1.0, 0.0, 0.0, 0.5);
+        let blue = LinearRgba(0.0, 0.0, 1.0, 0.5);
+        let LinearRgba(r, g, b, a) = red.over(&blue);
+        assert!((a - 0.75).abs() < 1e-6);
+        assert!((r - 2.0 / 3.0).abs() < 1e-6);
+        assert!(g.abs() < 1e-6);
+        assert!((b - 1.0 / 3.0).abs() < 1e-6);
+    }
+
+    // ── LinearRgba::average ─────────────────────────────────
+
+    #[test]
+    fn average_of_empty_slice_is_transparent() {
+        assert_eq!(LinearRgba::average(&[]), LinearRgba::TRANSPARENT);
+    }
+
+    #[test]
+    fn average_of_two_opaque_is_midpoint() {
+        let a = LinearRgba(0.2, 0.4, 1.0, 1.0);
+        let b = LinearRgba(0.6, 0.0, 0.5, 1.0);
+        let avg = LinearRgba::average(&[a, b]);
+        let mid = LinearRgba(
+            a.0 + 0.5 * (b.0 - a.0),
+            a.1 + 0.5 * (b.1 - a.1),
+            a.2 + 0.5 * (b.2 - a.2),
+            1.0,
+        );
+        assert!((avg.0 - mid.0).abs() < 1e-6);
+        assert!((avg.1 - mid.1).abs() < 1e-6);
+        assert!((avg.2 - mid.2).abs() < 1e-6);
+        assert!((avg.3 - mid.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn average_ignores_color_of_transparent_pixels() {
+        let red = LinearRgba(1.0, 0.0, 0.0, 1.0);
+        // A fully transparent white pixel must not lighten the red
+        let clear_white = LinearRgba(1.0, 1.0, 1.0, 0.0);
+        let avg = LinearRgba::average(&[red, clear_white]);
+        assert_eq!((avg.0, avg.1, avg.2), (1.0, 0.0, 0.0));
+        assert!((avg.3 - 0.5).abs() < 1e-6);
+
+        // A half transparent blue contributes half as much as the red
+        let blue = LinearRgba(0.0, 0.0, 1.0, 0.5);
+        let avg = LinearRgba::average(&[red, blue]);
+        assert!((avg.0 - 2.0 / 3.0).abs() < 1e-6);
+        assert!((avg.2 - 1.0 / 3.0).abs() < 1e-6);
+        assert!((avg.3 - 0.75).abs() < 1e-6);
+    }
+
     // ── Contrast ratio symmetry ─────────────────────────────
 
     #[cfg(feature = "std")]
@@ -2257,6 +3607,85 @@ mod tests {
         assert!((ratio - 21.0).abs() < 0.5);
     }
 
+    // ── SrgbaTuple::best_text_color ──────────────────────────
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn best_text_color_picks_white_on_dark_and_black_on_light() {
+        let black = SrgbaTuple(0., 0., 0., 1.);
+        let white = SrgbaTuple(1., 1., 1., 1.);
+        let dark = SrgbaTuple::from_str("#1e1e2e").unwrap();
+        let light = SrgbaTuple::from_str("#f0f0e0").unwrap();
+        assert_eq!(dark.best_text_color(&[black, white]), white);
+        assert_eq!(light.best_text_color(&[black, white]), black);
+        assert_eq!(dark.best_text_color(&[]), white);
+        assert_eq!(light.best_text_color(&[]), black);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn best_text_color_chooses_among_candidates() {
+        let bg = SrgbaTuple::from_str("#000080").unwrap();
+        let yellow = SrgbaTuple::from_str("yellow").unwrap();
+        let grey = SrgbaTuple::from_str("#606060").unwrap();
+        let navy = SrgbaTuple::from_str("#000070").unwrap();
+        assert_eq!(bg.best_text_color(&[navy, grey, yellow]), yellow);
+        assert_eq!(bg.best_text_color(&[navy]), navy);
+    }
+
+    // ── SrgbaTuple::adjust_lightness_oklab ───────────────────
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn adjust_lightness_oklab_changes_luminance() {
+        for color in ["#3366cc", "#cc3333", "#2e8b57", "#808080"] {
+            let c = SrgbaTuple::from_str(color).unwrap();
+            let lum = c.to_linear().relative_luminance();
+            let lighter = c.adjust_lightness_oklab(0.1);
+            let darker = c.adjust_lightness_oklab(-0.1);
+            assert!(lighter.to_linear().relative_luminance() > lum, "{}", color);
+            assert!(darker.to_linear().relative_luminance() < lum, "{}", color);
+            assert_eq!(lighter.3, c.3);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn adjust_lightness_oklab_preserves_hue() {
+        let hue = |c: SrgbaTuple| {
+            let [_, a, b, _] = c.to_linear().to_oklaba();
+            b.atan2(a)
+        };
+        // Moderately saturated, so that these steps stay in gamut
+        let c = SrgbaTuple::from_str("#5a78b4").unwrap();
+        for delta in [-0.15, -0.05, 0.05, 0.15] {
+            let adjusted = c.adjust_lightness_oklab(delta);
+            assert!(
+                (hue(adjusted) - hue(c)).abs() < 0.05,
+                "delta {} moved hue from {} to {}",
+                delta,
+                hue(c),
+                hue(adjusted)
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn adjust_lightness_oklab_clamps() {
+        let grey = SrgbaTuple::from_str("#808080").unwrap();
+        let white = grey.adjust_lightness_oklab(5.0);
+        assert!(
+            white.0 > 0.999 && white.1 > 0.999 && white.2 > 0.999,
+            "{:?}",
+            white
+        );
+        assert_eq!(
+            grey.adjust_lightness_oklab(-5.0).to_srgb_u8(),
+            (0, 0, 0, 255)
+        );
+    }
+
     // ── SrgbaTuple ensure_contrast_ratio ─────────────────────
 
     #[cfg(feature = "std")]
@@ -2287,6 +3716,81 @@ mod tests {
         assert!(v < 0.04045);
     }
 
+    // ── srgb_f32_to_linear / linear_to_srgb_f32 ─────────────
+
+    #[test]
+    fn srgb_f32_to_linear_endpoints() {
+        assert_eq!(srgb_f32_to_linear(0.0), 0.0);
+        assert!((srgb_f32_to_linear(1.0) - 1.0).abs() < 1e-6);
+        assert_eq!(linear_to_srgb_f32(0.0), 0.0);
+        assert!((linear_to_srgb_f32(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn srgb_f32_to_linear_threshold_boundary() {
+        // Both sides of the piecewise function meet at 0.04045
+        let below = srgb_f32_to_linear(0.04045);
+        let above = srgb_f32_to_linear(0.04046);
+        assert!((below - 0.04045 / 12.92).abs() < 1e-7);
+        assert!(above > below);
+        assert!(above - below < 1e-5);
+        assert!((linear_to_srgb_f32(below) - 0.04045).abs() < 1e-5);
+    }
+
+    #[test]
+    fn srgb_f32_linear_roundtrip() {
+        for i in 0..=100 {
+            let v = i as f32 / 100.0;
+            let rt = linear_to_srgb_f32(srgb_f32_to_linear(v));
+            assert!((rt - v).abs() < 1e-5, "{} roundtripped to {}", v, rt);
+        }
+    }
+
+    #[test]
+    fn srgb_f32_to_linear_matches_srgba_tuple() {
+        let lin = SrgbaTuple(0.2, 0.5, 0.9, 1.0).to_linear();
+        assert_eq!(lin.0, srgb_f32_to_linear(0.2));
+        assert_eq!(lin.1, srgb_f32_to_linear(0.5));
+        assert_eq!(lin.2, srgb_f32_to_linear(0.9));
+    }
+
+    // ── slice conversions ───────────────────────────────────
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn srgb8_slice_to_linear_matches_scalar() {
+        let src: Vec<u8> = (0..=255).collect();
+        let mut dst = vec![0.; src.len()];
+        srgb8_slice_to_linear(&src, &mut dst);
+        for (s, d) in src.iter().zip(&dst) {
+            assert_eq!(*d, srgb8_to_linear_f32(*s), "input {}", s);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn linear_slice_to_srgb8_matches_scalar() {
+        let mut src: Vec<f32> = (0..=255).map(srgb8_to_linear_f32).collect();
+        src.extend((0..=255).map(|v| v as f32 / 255.));
+        src.extend([-1.0, 2.0, 0.5]);
+        let mut dst = vec![0; src.len()];
+        linear_slice_to_srgb8(&src, &mut dst);
+        for (s, d) in src.iter().zip(&dst) {
+            assert_eq!(*d, linear_f32_to_srgb8(*s), "input {}", s);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn slice_conversions_roundtrip_all_bytes() {
+        let src: Vec<u8> = (0..=255).collect();
+        let mut linear = vec![0.; src.len()];
+        srgb8_slice_to_linear(&src, &mut linear);
+        let mut back = vec![0; src.len()];
+        linear_slice_to_srgb8(&linear, &mut back);
+        assert_eq!(back, src);
+    }
+
     // ── Third-pass expansion ────────────────────────────────────
 
     #[test]
@@ -7,7 +7,7 @@ use std::os::unix::net::UnixStream as StreamImpl;
 use std::os::windows::io::{
     AsRawSocket, AsSocket, BorrowedSocket, FromRawSocket, IntoRawSocket, RawSocket,
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 #[cfg(windows)]
 use uds_windows::UnixStream as StreamImpl;
 
@@ -29,6 +29,9 @@ use std::os::unix::net::SocketAddr;
 #[cfg(windows)]
 use uds_windows::SocketAddr;
 
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram as DatagramImpl;
+
 /// This wrapper makes UnixStream IoSafe on all platforms.
 /// This isn't strictly needed on unix, because async-io
 /// includes an impl for the std UnixStream, but on Windows
@@ -38,6 +41,17 @@ use uds_windows::SocketAddr;
 #[derive(Debug)]
 pub struct UnixStream(StreamImpl);
 
+/// The credentials of the process on the other end of a
+/// `UnixStream`, as captured by the kernel when it connected.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCred {
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+    /// Only available on Linux and Android
+    pub pid: Option<libc::pid_t>,
+}
+
 #[cfg(unix)]
 impl AsFd for UnixStream {
     fn as_fd(&self) -> BorrowedFd<'_> {
@@ -110,6 +124,291 @@ impl UnixStream {
     pub fn connect<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
         Ok(Self(StreamImpl::connect(path)?))
     }
+
+    /// Attempts a single non-blocking read, returning `Ok(None)` if
+    /// no data is available rather than waiting for some.
+    /// Unlike `set_nonblocking`, this doesn't change the blocking
+    /// mode of the socket, so it doesn't disturb other users of it.
+    #[cfg(unix)]
+    pub fn try_read(&self, buf: &mut [u8]) -> std::io::Result<Option<usize>> {
+        let res = unsafe {
+            libc::recv(
+                self.as_raw_fd(),
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                libc::MSG_DONTWAIT,
+            )
+        };
+        Self::dontwait_result(res)
+    }
+
+    /// Attempts a single non-blocking write, returning `Ok(None)` if
+    /// the socket buffer is full rather than waiting for space.
+    /// Like `try_read`, the blocking mode of the socket is unchanged.
+    #[cfg(unix)]
+    pub fn try_write(&self, buf: &[u8]) -> std::io::Result<Option<usize>> {
+        let res = unsafe {
+            libc::send(
+                self.as_raw_fd(),
+                buf.as_ptr().cast(),
+                buf.len(),
+                libc::MSG_DONTWAIT,
+            )
+        };
+        Self::dontwait_result(res)
+    }
+
+    #[cfg(unix)]
+    fn dontwait_result(res: libc::ssize_t) -> std::io::Result<Option<usize>> {
+        if res == -1 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::WouldBlock {
+                Ok(None)
+            } else {
+                Err(err)
+            }
+        } else {
+            Ok(Some(res as usize))
+        }
+    }
+
+    /// There is no per-call equivalent of `MSG_DONTWAIT` on Windows,
+    /// and temporarily switching the whole socket to non-blocking
+    /// mode would race with other users of it, so this always fails
+    /// with `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn try_read(&self, _buf: &mut [u8]) -> std::io::Result<Option<usize>> {
+        Err(Self::dontwait_unsupported())
+    }
+
+    /// See `try_read`; always fails with `ErrorKind::Unsupported`
+    /// on Windows.
+    #[cfg(windows)]
+    pub fn try_write(&self, _buf: &[u8]) -> std::io::Result<Option<usize>> {
+        Err(Self::dontwait_unsupported())
+    }
+
+    #[cfg(windows)]
+    fn dontwait_unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "non-blocking reads and writes are not supported on Windows",
+        )
+    }
+
+    /// Waits until the socket is readable, or until `timeout` elapses,
+    /// returning whether it became readable.  No data is consumed.
+    /// A `timeout` of `None` waits indefinitely.
+    /// The socket also counts as readable once the peer has hung up,
+    /// as a read would then return immediately.
+    #[cfg(unix)]
+    pub fn wait_readable(&self, timeout: Option<std::time::Duration>) -> std::io::Result<bool> {
+        self.poll_until(libc::POLLIN, timeout)
+    }
+
+    /// Waits until the socket is writable, or until `timeout` elapses,
+    /// returning whether it became writable.  No data is written.
+    /// A `timeout` of `None` waits indefinitely.
+    #[cfg(unix)]
+    pub fn wait_writable(&self, timeout: Option<std::time::Duration>) -> std::io::Result<bool> {
+        self.poll_until(libc::POLLOUT, timeout)
+    }
+
+    #[cfg(unix)]
+    fn poll_until(
+        &self,
+        events: libc::c_short,
+        timeout: Option<std::time::Duration>,
+    ) -> std::io::Result<bool> {
+        poll_fd(self.as_raw_fd(), events, timeout)
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn wait_readable(&self, _timeout: Option<std::time::Duration>) -> std::io::Result<bool> {
+        Err(Self::wait_unsupported())
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn wait_writable(&self, _timeout: Option<std::time::Duration>) -> std::io::Result<bool> {
+        Err(Self::wait_unsupported())
+    }
+
+    #[cfg(windows)]
+    fn wait_unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "waiting for socket readiness is not supported on Windows",
+        )
+    }
+
+    /// Returns the credentials of the peer process
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn peer_cred(&self) -> std::io::Result<PeerCred> {
+        let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of_val(&cred) as libc::socklen_t;
+        let res = unsafe {
+            libc::getsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                &mut cred as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(PeerCred {
+            uid: cred.uid,
+            gid: cred.gid,
+            pid: Some(cred.pid),
+        })
+    }
+
+    /// Returns the credentials of the peer process
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    pub fn peer_cred(&self) -> std::io::Result<PeerCred> {
+        let mut uid: libc::uid_t = 0;
+        let mut gid: libc::gid_t = 0;
+        if unsafe { libc::getpeereid(self.as_raw_fd(), &mut uid, &mut gid) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(PeerCred {
+            uid,
+            gid,
+            pid: None,
+        })
+    }
+
+    /// Enables or disables `SO_PASSCRED`, which Linux requires
+    /// before it will deliver the sender's credentials as
+    /// `SCM_CREDENTIALS` ancillary data to `recvmsg`.
+    #[cfg(target_os = "linux")]
+    pub fn set_passcred(&self, on: bool) -> std::io::Result<()> {
+        let value = libc::c_int::from(on);
+        let res = unsafe {
+            libc::setsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PASSCRED,
+                &value as *const _ as *const libc::c_void,
+                std::mem::size_of_val(&value) as libc::socklen_t,
+            )
+        };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Sets the size of the kernel receive buffer (`SO_RCVBUF`).
+    /// The kernel may round the requested size up, or clamp it to
+    /// a system limit; use `recv_buffer_size` to see what it chose.
+    #[cfg(unix)]
+    pub fn set_recv_buffer_size(&self, size: usize) -> std::io::Result<()> {
+        self.set_buffer_size(libc::SO_RCVBUF, size)
+    }
+
+    /// Sets the size of the kernel send buffer (`SO_SNDBUF`).
+    /// As with `set_recv_buffer_size`, the effective size may differ.
+    #[cfg(unix)]
+    pub fn set_send_buffer_size(&self, size: usize) -> std::io::Result<()> {
+        self.set_buffer_size(libc::SO_SNDBUF, size)
+    }
+
+    /// Returns the size of the kernel receive buffer (`SO_RCVBUF`).
+    #[cfg(unix)]
+    pub fn recv_buffer_size(&self) -> std::io::Result<usize> {
+        self.buffer_size(libc::SO_RCVBUF)
+    }
+
+    /// Returns the size of the kernel send buffer (`SO_SNDBUF`).
+    #[cfg(unix)]
+    pub fn send_buffer_size(&self) -> std::io::Result<usize> {
+        self.buffer_size(libc::SO_SNDBUF)
+    }
+
+    #[cfg(unix)]
+    fn set_buffer_size(&self, option: libc::c_int, size: usize) -> std::io::Result<()> {
+        let value = libc::c_int::try_from(size).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("buffer size {size} is too large"),
+            )
+        })?;
+        let res = unsafe {
+            libc::setsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                option,
+                &value as *const _ as *const libc::c_void,
+                std::mem::size_of_val(&value) as libc::socklen_t,
+            )
+        };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn buffer_size(&self, option: libc::c_int) -> std::io::Result<usize> {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of_val(&value) as libc::socklen_t;
+        let res = unsafe {
+            libc::getsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                option,
+                &mut value as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(value as usize)
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn set_recv_buffer_size(&self, _size: usize) -> std::io::Result<()> {
+        Err(Self::buffer_size_unsupported())
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn set_send_buffer_size(&self, _size: usize) -> std::io::Result<()> {
+        Err(Self::buffer_size_unsupported())
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn recv_buffer_size(&self) -> std::io::Result<usize> {
+        Err(Self::buffer_size_unsupported())
+    }
+
+    /// Not implemented on Windows; always fails with
+    /// `ErrorKind::Unsupported`.
+    #[cfg(windows)]
+    pub fn send_buffer_size(&self) -> std::io::Result<usize> {
+        Err(Self::buffer_size_unsupported())
+    }
+
+    #[cfg(windows)]
+    fn buffer_size_unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "socket buffer sizes are not supported on Windows",
+        )
+    }
 }
 
 impl std::ops::Deref for UnixStream {
@@ -125,32 +424,401 @@ impl std::ops::DerefMut for UnixStream {
     }
 }
 
-pub struct UnixListener(ListenerImpl);
+/// Waits until `fd` is ready for any of `events`, or until `timeout`
+/// elapses, returning whether it became ready.
+/// A `timeout` of `None`, or one too large to represent as a
+/// deadline, waits indefinitely.
+#[cfg(unix)]
+fn poll_fd(
+    fd: RawFd,
+    events: libc::c_short,
+    timeout: Option<std::time::Duration>,
+) -> std::io::Result<bool> {
+    let deadline = timeout.and_then(|t| std::time::Instant::now().checked_add(t));
+    loop {
+        let timeout_ms = match deadline {
+            None => -1,
+            Some(deadline) => {
+                let remain = deadline.saturating_duration_since(std::time::Instant::now());
+                // Round up, so that a sub-millisecond remainder
+                // doesn't turn into a busy loop of zero timeouts
+                remain
+                    .as_micros()
+                    .div_ceil(1000)
+                    .min(libc::c_int::MAX as u128) as libc::c_int
+            }
+        };
+        let mut pfd = libc::pollfd {
+            fd,
+            events,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut pfd, 1, timeout_ms) } {
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            }
+            0 => return Ok(false),
+            _ => return Ok(true),
+        }
+    }
+}
+
+pub struct UnixListener {
+    listener: ListenerImpl,
+    path: PathBuf,
+    remove_on_drop: bool,
+}
 
 impl UnixListener {
     pub fn bind<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
-        Ok(Self(ListenerImpl::bind(path)?))
+        let path = path.as_ref();
+        Ok(Self {
+            listener: ListenerImpl::bind(path)?,
+            path: path.to_path_buf(),
+            remove_on_drop: false,
+        })
+    }
+
+    /// Like `bind`, but listens with the specified backlog rather than
+    /// the platform default, so that a burst of connecting clients
+    /// doesn't overflow the accept queue.
+    /// On Windows the backlog is ignored and the default is used.
+    pub fn bind_with_backlog<P: AsRef<Path>>(path: P, backlog: i32) -> std::io::Result<Self> {
+        let listener = Self::bind(path)?;
+        #[cfg(unix)]
+        {
+            // std has already put the socket into the listening state;
+            // calling listen again on it adjusts the backlog.
+            if unsafe { libc::listen(listener.as_raw_fd(), backlog) } == -1 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        #[cfg(windows)]
+        let _ = backlog;
+        Ok(listener)
+    }
+
+    /// Wraps an already bound and listening unix socket, such as the
+    /// one passed as fd 3 under systemd socket activation (LISTEN_FDS).
+    /// Fails without taking ownership of `fd` if it isn't a unix
+    /// domain socket in the listening state.
+    ///
+    /// # Safety
+    /// On success the returned listener owns `fd` and will close it
+    /// when dropped, so `fd` must be open and not owned elsewhere.
+    #[cfg(unix)]
+    pub unsafe fn from_raw_fd_checked(fd: RawFd) -> std::io::Result<Self> {
+        let mut storage: libc::sockaddr_storage = std::mem::zeroed();
+        let mut len = std::mem::size_of_val(&storage) as libc::socklen_t;
+        if libc::getsockname(fd, &mut storage as *mut _ as *mut libc::sockaddr, &mut len) == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        if storage.ss_family as libc::c_int != libc::AF_UNIX {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("fd {fd} is not a unix domain socket"),
+            ));
+        }
+
+        let mut accepting: libc::c_int = 0;
+        let mut len = std::mem::size_of_val(&accepting) as libc::socklen_t;
+        if libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_ACCEPTCONN,
+            &mut accepting as *mut _ as *mut libc::c_void,
+            &mut len,
+        ) == -1
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+        if accepting == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("fd {fd} is not a listening socket"),
+            ));
+        }
+
+        let listener = ListenerImpl::from_raw_fd(fd);
+        let path = listener
+            .local_addr()
+            .ok()
+            .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+            .unwrap_or_default();
+        Ok(Self {
+            listener,
+            path,
+            remove_on_drop: false,
+        })
+    }
+
+    /// Like `bind`, but the socket file created by this call is
+    /// removed when the returned listener is dropped, so that it
+    /// doesn't linger and cause a later `bind` to fail.
+    pub fn bind_with_cleanup<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let mut listener = Self::bind(path)?;
+        listener.remove_on_drop = true;
+        Ok(listener)
+    }
+
+    /// Returns the path that this listener was bound to, if any.
+    /// A listener adopted via `from_raw_fd_checked` may be unnamed.
+    pub fn local_path(&self) -> Option<&Path> {
+        if self.path.as_os_str().is_empty() {
+            None
+        } else {
+            Some(&self.path)
+        }
     }
 
     pub fn accept(&self) -> std::io::Result<(UnixStream, SocketAddr)> {
-        let (stream, addr) = self.0.accept()?;
+        let (stream, addr) = self.listener.accept()?;
         Ok((UnixStream(stream), addr))
     }
 
     pub fn incoming(&self) -> impl Iterator<Item = std::io::Result<UnixStream>> + '_ {
-        self.0.incoming().map(|r| r.map(UnixStream))
+        self.listener.incoming().map(|r| r.map(UnixStream))
+    }
+
+    /// Like `accept`, but gives up and returns `Ok(None)` if no client
+    /// connects within `timeout`; eg: so that a server can wake up
+    /// periodically to check for a shutdown request.
+    /// The accepted stream is in blocking mode.
+    /// The listener's own flags are left alone, so if several threads
+    /// wait on the same listener then it should be non-blocking;
+    /// otherwise a thread that loses the race for a connection blocks
+    /// in accept until the next one arrives.
+    /// A `timeout` too large to represent as a deadline waits
+    /// indefinitely.
+    #[cfg(unix)]
+    pub fn accept_timeout(
+        &self,
+        timeout: std::time::Duration,
+    ) -> std::io::Result<Option<(UnixStream, SocketAddr)>> {
+        let deadline = std::time::Instant::now().checked_add(timeout);
+        loop {
+            let remain = deadline.map(|d| d.saturating_duration_since(std::time::Instant::now()));
+            if !poll_fd(self.as_raw_fd(), libc::POLLIN, remain)? {
+                return Ok(None);
+            }
+
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    // On Linux, std accepts with accept4(SOCK_CLOEXEC), and the
+                    // new socket never inherits O_NONBLOCK from the listener.
+                    // Elsewhere it may, but only if the listener has it set.
+                    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                    {
+                        let flags = unsafe { libc::fcntl(self.as_raw_fd(), libc::F_GETFL) };
+                        if flags == -1 || flags & libc::O_NONBLOCK != 0 {
+                            stream.set_nonblocking(false)?;
+                        }
+                    }
+                    return Ok(Some((UnixStream(stream), addr)));
+                }
+                // Another thread accepted the connection that woke us up
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted
+                    ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Like `accept`, but gives up and returns `Ok(None)` if no client
+    /// connects within `timeout`.
+    /// There is no way to wait for these sockets to become ready, so
+    /// this polls accept every few milliseconds, which is only
+    /// possible if the caller has made the listener non-blocking.
+    /// The listener's mode is left alone, so on a blocking listener
+    /// this waits in accept for as long as it takes a client to
+    /// connect.  The accepted stream is in blocking mode.
+    #[cfg(windows)]
+    pub fn accept_timeout(
+        &self,
+        timeout: std::time::Duration,
+    ) -> std::io::Result<Option<(UnixStream, SocketAddr)>> {
+        let deadline = std::time::Instant::now().checked_add(timeout);
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    // The stream inherits non-blocking mode from the listener
+                    stream.set_nonblocking(false)?;
+                    return Ok(Some((UnixStream(stream), addr)));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    let remain = match deadline {
+                        Some(deadline) => {
+                            deadline.saturating_duration_since(std::time::Instant::now())
+                        }
+                        None => std::time::Duration::MAX,
+                    };
+                    if remain.is_zero() {
+                        return Ok(None);
+                    }
+                    std::thread::sleep(remain.min(std::time::Duration::from_millis(10)));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Accepts a connection, but only hands it back if `pred`
+    /// approves of the peer's credentials; otherwise the stream is
+    /// dropped, closing the connection, and `Ok(None)` is returned.
+    /// Useful for refusing connections from unexpected users.
+    #[cfg(unix)]
+    pub fn accept_if(
+        &self,
+        pred: impl Fn(&PeerCred) -> bool,
+    ) -> std::io::Result<Option<(UnixStream, SocketAddr)>> {
+        let (stream, addr) = self.accept()?;
+        if pred(&stream.peer_cred()?) {
+            Ok(Some((stream, addr)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Drop for UnixListener {
+    fn drop(&mut self) {
+        if self.remove_on_drop {
+            let _ = std::fs::remove_file(&self.path);
+        }
     }
 }
 
 impl std::ops::Deref for UnixListener {
     type Target = ListenerImpl;
     fn deref(&self) -> &ListenerImpl {
-        &self.0
+        &self.listener
     }
 }
 
 impl std::ops::DerefMut for UnixListener {
     fn deref_mut(&mut self) -> &mut ListenerImpl {
+        &mut self.listener
+    }
+}
+
+/// A datagram (SOCK_DGRAM) unix domain socket, for fire-and-forget
+/// messages that don't need the framing of a stream.
+/// uds_windows has no datagram support, so on Windows this cannot
+/// be constructed and `bind` fails with `ErrorKind::Unsupported`.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixDatagram(DatagramImpl);
+
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct UnixDatagram(std::convert::Infallible);
+
+#[cfg(unix)]
+impl AsFd for UnixDatagram {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+#[cfg(unix)]
+impl IntoRawFd for UnixDatagram {
+    fn into_raw_fd(self) -> RawFd {
+        self.0.into_raw_fd()
+    }
+}
+#[cfg(unix)]
+impl FromRawFd for UnixDatagram {
+    unsafe fn from_raw_fd(fd: RawFd) -> UnixDatagram {
+        UnixDatagram(DatagramImpl::from_raw_fd(fd))
+    }
+}
+#[cfg(unix)]
+impl AsRawFd for UnixDatagram {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+#[cfg(feature = "async-io")]
+unsafe impl async_io::IoSafe for UnixDatagram {}
+
+#[cfg(unix)]
+impl UnixDatagram {
+    pub fn bind<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self(DatagramImpl::bind(path)?))
+    }
+
+    pub fn connect<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        self.0.connect(path)
+    }
+
+    pub fn send(&self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.send(buf)
+    }
+
+    pub fn recv(&self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.recv(buf)
+    }
+
+    pub fn send_to<P: AsRef<Path>>(&self, buf: &[u8], path: P) -> std::io::Result<usize> {
+        self.0.send_to(buf, path)
+    }
+
+    pub fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        self.0.recv_from(buf)
+    }
+}
+
+#[cfg(windows)]
+impl UnixDatagram {
+    fn unsupported() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "unix datagram sockets are not supported on Windows",
+        )
+    }
+
+    pub fn bind<P: AsRef<Path>>(_path: P) -> std::io::Result<Self> {
+        Err(Self::unsupported())
+    }
+
+    pub fn connect<P: AsRef<Path>>(&self, _path: P) -> std::io::Result<()> {
+        match self.0 {}
+    }
+
+    pub fn send(&self, _buf: &[u8]) -> std::io::Result<usize> {
+        match self.0 {}
+    }
+
+    pub fn recv(&self, _buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0 {}
+    }
+
+    pub fn send_to<P: AsRef<Path>>(&self, _buf: &[u8], _path: P) -> std::io::Result<usize> {
+        match self.0 {}
+    }
+
+    pub fn recv_from(&self, _buf: &mut [u8]) -> std::io::Result<(usize, SocketAddr)> {
+        match self.0 {}
+    }
+}
+
+#[cfg(unix)]
+impl std::ops::Deref for UnixDatagram {
+    type Target = DatagramImpl;
+    fn deref(&self) -> &DatagramImpl {
+        &self.0
+    }
+}
+
+#[cfg(unix)]
+impl std::ops::DerefMut for UnixDatagram {
+    fn deref_mut(&mut self) -> &mut DatagramImpl {
         &mut self.0
     }
 }
@@ -189,6 +857,406 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn listener_local_path_is_bound_path() {
+        let path = temp_socket_path("local_path");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        assert_eq!(listener.local_path(), Some(path.as_path()));
+        drop(listener);
+        cleanup(&path);
+    }
+
+    #[test]
+    fn listener_bind_leaves_file_on_drop() {
+        let path = temp_socket_path("no_cleanup");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        drop(listener);
+        assert!(path.exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn listener_bind_with_cleanup_removes_file_on_drop() {
+        let path = temp_socket_path("with_cleanup");
+        cleanup(&path);
+        let listener = UnixListener::bind_with_cleanup(&path).unwrap();
+        assert!(path.exists());
+        assert_eq!(listener.local_path(), Some(path.as_path()));
+        drop(listener);
+        assert!(!path.exists());
+
+        // The path can be bound again without manual cleanup
+        let listener = UnixListener::bind_with_cleanup(&path).unwrap();
+        drop(listener);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_from_raw_fd_checked_accepts() {
+        let path = temp_socket_path("from-fd");
+        cleanup(&path);
+        let fd = ListenerImpl::bind(&path).unwrap().into_raw_fd();
+        let listener = unsafe { UnixListener::from_raw_fd_checked(fd) }.unwrap();
+        assert_eq!(listener.as_raw_fd(), fd);
+        assert_eq!(listener.local_path(), Some(path.as_path()));
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        let (mut server, _addr) = listener.accept().unwrap();
+        client.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+
+        drop(listener);
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_from_raw_fd_checked_rejects_non_listeners() {
+        let path = temp_socket_path("from-fd-reject");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = UnixStream::connect(&path).unwrap();
+        match unsafe { UnixListener::from_raw_fd_checked(client.as_raw_fd()) } {
+            Err(err) => assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput),
+            Ok(_) => panic!("a connected stream is not a listener"),
+        }
+
+        let file = std::fs::File::open(std::env::current_exe().unwrap()).unwrap();
+        assert!(unsafe { UnixListener::from_raw_fd_checked(file.as_raw_fd()) }.is_err());
+
+        drop(client);
+        drop(listener);
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_bind_with_backlog_accepts() {
+        let path = temp_socket_path("backlog");
+        cleanup(&path);
+        let listener = UnixListener::bind_with_backlog(&path, 2).unwrap();
+        assert_eq!(listener.local_path(), Some(path.as_path()));
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        let (mut server, _addr) = listener.accept().unwrap();
+        client.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+
+        drop(listener);
+        assert!(path.exists());
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_plain_bind_still_accepts() {
+        let path = temp_socket_path("default_backlog");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let _client = UnixStream::connect(&path).unwrap();
+        assert!(listener.accept().is_ok());
+        drop(listener);
+        assert!(path.exists());
+        cleanup(&path);
+    }
+
+    #[test]
+    fn listener_bind_with_backlog_to_invalid_path_fails() {
+        assert!(UnixListener::bind_with_backlog("/nonexistent/dir/socket.sock", 4).is_err());
+    }
+
+    #[test]
+    fn listener_bind_with_cleanup_failure_leaves_existing_file() {
+        let path = temp_socket_path("cleanup_existing");
+        cleanup(&path);
+        let _first = UnixListener::bind(&path).unwrap();
+        assert!(UnixListener::bind_with_cleanup(&path).is_err());
+        assert!(path.exists());
+        cleanup(&path);
+    }
+
+    // ── UnixStream try_read / try_write ────────────────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_try_read_empty_returns_none_without_blocking() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let mut a = UnixStream(a);
+        let mut buf = [0u8; 16];
+        assert_eq!(a.try_read(&mut buf).unwrap(), None);
+
+        b.write_all(b"ping").unwrap();
+        assert_eq!(a.try_read(&mut buf).unwrap(), Some(4));
+        assert_eq!(&buf[..4], b"ping");
+
+        // The socket itself is still in blocking mode, so a plain
+        // read waits out its timeout rather than failing immediately
+        a.set_read_timeout(Some(std::time::Duration::from_millis(50)))
+            .unwrap();
+        let start = std::time::Instant::now();
+        assert!(a.read(&mut buf).is_err());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(40));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_try_write_returns_none_when_full() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        assert_eq!(a.try_write(b"hello").unwrap(), Some(5));
+        let mut buf = [0u8; 5];
+        b.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+
+        let chunk = [0u8; 4096];
+        let mut filled = false;
+        for _ in 0..100_000 {
+            if a.try_write(&chunk).unwrap().is_none() {
+                filled = true;
+                break;
+            }
+        }
+        assert!(filled);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_try_read_after_peer_closed_returns_zero() {
+        let (a, b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        drop(b);
+        let mut buf = [0u8; 4];
+        assert_eq!(a.try_read(&mut buf).unwrap(), Some(0));
+    }
+
+    // ── UnixStream wait_readable / wait_writable ───────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_wait_readable_times_out_then_becomes_ready() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        let start = std::time::Instant::now();
+        assert!(!a
+            .wait_readable(Some(std::time::Duration::from_millis(50)))
+            .unwrap());
+        assert!(start.elapsed() >= std::time::Duration::from_millis(40));
+
+        b.write_all(b"ping").unwrap();
+        assert!(a
+            .wait_readable(Some(std::time::Duration::from_secs(5)))
+            .unwrap());
+        assert!(a.wait_readable(None).unwrap());
+
+        // Waiting doesn't consume the data
+        let mut buf = [0u8; 4];
+        assert_eq!(a.try_read(&mut buf).unwrap(), Some(4));
+        assert_eq!(&buf, b"ping");
+        assert!(!a.wait_readable(Some(std::time::Duration::ZERO)).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_wait_readable_wakes_on_peer_write_from_thread() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            b.write_all(b"x").unwrap();
+            b
+        });
+        assert!(a
+            .wait_readable(Some(std::time::Duration::from_secs(5)))
+            .unwrap());
+        drop(writer.join().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_wait_with_huge_timeout_does_not_overflow() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        b.write_all(b"x").unwrap();
+        assert!(a.wait_readable(Some(std::time::Duration::MAX)).unwrap());
+        assert!(a.wait_writable(Some(std::time::Duration::MAX)).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_wait_writable() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        assert!(a
+            .wait_writable(Some(std::time::Duration::from_millis(50)))
+            .unwrap());
+
+        let chunk = [0u8; 4096];
+        while a.try_write(&chunk).unwrap().is_some() {}
+        assert!(!a
+            .wait_writable(Some(std::time::Duration::from_millis(20)))
+            .unwrap());
+    }
+
+    // ── UnixStream buffer sizes ────────────────────────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_set_buffer_sizes_reads_back_at_least_requested() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        a.set_recv_buffer_size(64 * 1024).unwrap();
+        assert!(a.recv_buffer_size().unwrap() >= 64 * 1024);
+        a.set_send_buffer_size(64 * 1024).unwrap();
+        assert!(a.send_buffer_size().unwrap() >= 64 * 1024);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_set_buffer_size_rejects_oversized_value() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        let err = a.set_recv_buffer_size(usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    // ── peer_cred / accept_if ──────────────────────────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_peer_cred_is_current_user() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let cred = UnixStream(a).peer_cred().unwrap();
+        assert_eq!(cred.uid, unsafe { libc::geteuid() });
+        assert_eq!(cred.gid, unsafe { libc::getegid() });
+        #[cfg(target_os = "linux")]
+        assert_eq!(cred.pid, Some(std::process::id() as libc::pid_t));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn listener_accept_if_checks_peer_uid() {
+        let path = temp_socket_path("accept_if");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let uid = unsafe { libc::geteuid() };
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        let (mut server, _) = listener
+            .accept_if(|cred| cred.uid == uid)
+            .unwrap()
+            .expect("current uid is admitted");
+        client.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+
+        let mut rejected = UnixStream::connect(&path).unwrap();
+        assert!(listener.accept_if(|_| false).unwrap().is_none());
+        // The server side was dropped, so the client sees EOF
+        let mut buf = [0u8; 1];
+        assert_eq!(rejected.read(&mut buf).unwrap(), 0);
+        cleanup(&path);
+    }
+
+    // ── set_passcred ───────────────────────────────────────────
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn stream_set_passcred_delivers_credentials() {
+        let (a, mut b) = std::os::unix::net::UnixStream::pair().unwrap();
+        let a = UnixStream(a);
+        a.set_passcred(true).unwrap();
+        b.write_all(b"x").unwrap();
+
+        let mut data = [0u8; 1];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr().cast(),
+            iov_len: data.len(),
+        };
+        let space = unsafe { libc::CMSG_SPACE(std::mem::size_of::<libc::ucred>() as u32) } as usize;
+        // u64 storage keeps the control buffer aligned for cmsghdr
+        let mut control = vec![0u64; space.div_ceil(8)];
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = space as _;
+        let n = unsafe { libc::recvmsg(a.as_raw_fd(), &mut msg, 0) };
+        assert_eq!(n, 1, "{}", std::io::Error::last_os_error());
+
+        let cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+        assert!(!cmsg.is_null());
+        let cred = unsafe {
+            assert_eq!((*cmsg).cmsg_level, libc::SOL_SOCKET);
+            assert_eq!((*cmsg).cmsg_type, libc::SCM_CREDENTIALS);
+            std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::ucred)
+        };
+        assert_eq!(cred.uid, unsafe { libc::geteuid() });
+        assert_eq!(cred.pid, std::process::id() as libc::pid_t);
+
+        a.set_passcred(false).unwrap();
+    }
+
+    // ── UnixDatagram ───────────────────────────────────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn datagram_bound_pair_send_recv() {
+        let a_path = temp_socket_path("dgram_a");
+        let b_path = temp_socket_path("dgram_b");
+        cleanup(&a_path);
+        cleanup(&b_path);
+        let a = UnixDatagram::bind(&a_path).unwrap();
+        let b = UnixDatagram::bind(&b_path).unwrap();
+        a.connect(&b_path).unwrap();
+        b.connect(&a_path).unwrap();
+
+        assert_eq!(a.send(b"ping").unwrap(), 4);
+        let mut buf = [0u8; 16];
+        let n = b.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"ping");
+
+        assert_eq!(b.send(b"pong").unwrap(), 4);
+        let n = a.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"pong");
+        cleanup(&a_path);
+        cleanup(&b_path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn datagram_send_to_recv_from() {
+        let a_path = temp_socket_path("dgram_to_a");
+        let b_path = temp_socket_path("dgram_to_b");
+        cleanup(&a_path);
+        cleanup(&b_path);
+        let a = UnixDatagram::bind(&a_path).unwrap();
+        let b = UnixDatagram::bind(&b_path).unwrap();
+
+        assert_eq!(a.send_to(b"hello", &b_path).unwrap(), 5);
+        let mut buf = [0u8; 16];
+        let (n, addr) = b.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"hello");
+        assert_eq!(addr.as_pathname(), Some(a_path.as_path()));
+        cleanup(&a_path);
+        cleanup(&b_path);
+    }
+
+    #[test]
+    fn datagram_bind_to_invalid_path_fails() {
+        let err = UnixDatagram::bind("/nonexistent/dir/dgram.sock").unwrap_err();
+        if cfg!(windows) {
+            assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        }
+    }
+
     // ── Connect + Accept ───────────────────────────────────────
 
     #[test]
@@ -1222,6 +2290,98 @@ mod tests {
         cleanup(&path);
     }
 
+    // ── accept_timeout ──────────────────────────────────────
+
+    #[cfg(unix)]
+    #[test]
+    fn accept_timeout_on_idle_listener_returns_none() {
+        let path = temp_socket_path("accept_timeout_idle");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let start = std::time::Instant::now();
+        let accepted = listener
+            .accept_timeout(std::time::Duration::from_millis(100))
+            .unwrap();
+        let elapsed = start.elapsed();
+        assert!(accepted.is_none());
+        assert!(
+            elapsed >= std::time::Duration::from_millis(90),
+            "{elapsed:?}"
+        );
+        assert!(elapsed < std::time::Duration::from_secs(2), "{elapsed:?}");
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accept_timeout_returns_connection() {
+        let path = temp_socket_path("accept_timeout_conn");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut client = UnixStream::connect(&path).unwrap();
+                client.write_all(b"hi").unwrap();
+                client
+            }
+        });
+        let (mut server, _addr) = listener
+            .accept_timeout(std::time::Duration::from_secs(5))
+            .unwrap()
+            .expect("client should connect in time");
+        let _client = client.join().unwrap();
+
+        let mut buf = [0u8; 2];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+
+        // Neither the listener nor the stream is left non-blocking
+        for fd in [listener.as_raw_fd(), server.as_raw_fd()] {
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            assert_eq!(flags & libc::O_NONBLOCK, 0);
+        }
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accept_timeout_with_huge_timeout_does_not_overflow() {
+        let path = temp_socket_path("accept_timeout_huge");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let _client = UnixStream::connect(&path).unwrap();
+        assert!(listener
+            .accept_timeout(std::time::Duration::MAX)
+            .unwrap()
+            .is_some());
+        cleanup(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn accept_timeout_leaves_nonblocking_listener_alone() {
+        let path = temp_socket_path("accept_timeout_nonblock");
+        cleanup(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        assert!(listener
+            .accept_timeout(std::time::Duration::from_millis(20))
+            .unwrap()
+            .is_none());
+
+        let _client = UnixStream::connect(&path).unwrap();
+        let (server, _addr) = listener
+            .accept_timeout(std::time::Duration::from_secs(5))
+            .unwrap()
+            .expect("client should connect in time");
+
+        let nonblocking = |fd| unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_NONBLOCK != 0;
+        assert!(nonblocking(listener.as_raw_fd()));
+        assert!(!nonblocking(server.as_raw_fd()));
+        cleanup(&path);
+    }
+
     // ── Stream survives listener drop ───────────────────────
 
     #[test]